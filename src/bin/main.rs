@@ -3,15 +3,13 @@
 #![allow(dead_code)]
 #![feature(allocator_api, new_zeroed_alloc)]
 
-use core::cell::RefCell;
-
-use critical_section::Mutex;
 use display::config::DMA_CHUNK_SIZE;
 
+use embassy_sync::channel::Channel;
 use embassy_time::{Delay, Duration, Ticker, Timer};
 use embedded_graphics::{
     pixelcolor::Rgb888,
-    prelude::{Point, RgbColor},
+    prelude::RgbColor,
     primitives::{Circle, PrimitiveStyleBuilder, StyledDrawable},
 };
 use esp_alloc::HeapStats;
@@ -19,16 +17,27 @@ use esp_hal::{
     clock::CpuClock,
     dma::{DmaRxBuf, DmaTxBuf},
     dma_buffers,
-    gpio::{Event, Input, InputConfig, Io, OutputPin, Pull},
-    handler,
+    gpio::{Input, InputConfig, Io, OutputPin, Pull},
     i2c::master::{Config, I2c},
     ledc::Ledc,
     spi::{self, master::Spi},
     time::Rate,
     timer::systimer::SystemTimer,
 };
-use lib::display::{self, config::ESP_PANEL_LCD_SPI_CLK_MHZ, draw::Spd2010};
-use spd2010::touch::{self, InterruptInput, SPD2010Touch, TouchData};
+use lib::display::{
+    self,
+    backlight::{self, Backlight},
+    config::ESP_PANEL_LCD_SPI_CLK_MHZ,
+    draw::Spd2010,
+    tearing::TearingMode,
+};
+use lib::touch::{
+    controller::Touch,
+    event::{TouchChannel, TouchPhase},
+    filter::TouchFilter,
+    interrupt,
+};
+use spd2010::touch as spd2010_touch;
 use waveshare_touch_lcd_1_46 as lib;
 
 use embassy_executor::Spawner;
@@ -48,39 +57,6 @@ const ESP_PANEL_LCD_SPI_IO_CS: u8 = 21;
 const EXAMPLE_LCD_PIN_NUM_RST: i8 = -1; // EXIO2
 const EXAMPLE_LCD_PIN_NUM_BK_LIGHT: u8 = 5;
 
-struct TouchInterrupt<'a> {
-    interrupt_input: Input<'a>,
-    interrupt_flag: bool,
-}
-
-impl<'a> TouchInterrupt<'a> {
-    fn new(interrupt_input: Input<'a>) -> Self {
-        Self {
-            interrupt_input,
-            interrupt_flag: false,
-        }
-    }
-    fn possible_interrupt(&mut self) {
-        if self.interrupt_input.is_interrupt_set() {
-            println!("Interrupt!");
-            self.interrupt_flag = true;
-            self.interrupt_input.clear_interrupt();
-        }
-    }
-}
-
-impl<'a> InterruptInput for TouchInterrupt<'a> {
-    fn get_interrupt_flag(&self) -> bool {
-        self.interrupt_flag
-    }
-    fn clear_interrupt_flag(&mut self) {
-        self.interrupt_flag = false
-    }
-    fn get_interrupt_state(&self) -> bool {
-        self.interrupt_input.is_high()
-    }
-}
-
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
     println!("{}", info);
@@ -96,8 +72,7 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
 
 extern crate alloc;
 
-// critical_section mutex requires RefCell for interior mutability
-static TOUCH_INTERRUPT: Mutex<RefCell<Option<TouchInterrupt>>> = Mutex::new(RefCell::new(None));
+static TOUCH_EVENTS: TouchChannel = Channel::new();
 
 #[esp_hal_embassy::main]
 async fn main(_spawner: Spawner) {
@@ -105,7 +80,7 @@ async fn main(_spawner: Spawner) {
     let peripherals = esp_hal::init(config);
 
     let mut io = Io::new(peripherals.IO_MUX);
-    io.set_interrupt_handler(handler);
+    io.set_interrupt_handler(interrupt::interrupt_handler);
 
     let timer0 = SystemTimer::new(peripherals.SYSTIMER);
     esp_hal_embassy::init(timer0.alarm0);
@@ -183,36 +158,40 @@ async fn main(_spawner: Spawner) {
 
     let mut ledc: Ledc = Ledc::new(ledc_pin);
 
-    display::backlight_init(&mut ledc, backlight_pwm_pin);
+    let backlight_timer = backlight::timer(&mut ledc);
+    let mut backlight = Backlight::new(&ledc, &backlight_timer, backlight_pwm_pin, 0);
 
-    let mut spd2010 = Spd2010::new(spi, peripherals.GPIO18);
+    let tear_input = Input::new(
+        peripherals.GPIO18,
+        InputConfig::default().with_pull(Pull::Up),
+    );
+    let mut spd2010: Spd2010<_, _, Rgb888> = Spd2010::new(spi, tear_input);
 
     spd2010.init().await.unwrap();
 
-    let config = InputConfig::default().with_pull(Pull::Up);
-    let mut touch_interrupt = Input::new(peripherals.GPIO4, config);
-    touch_interrupt.listen(Event::FallingEdge);
+    spd2010
+        .set_tearing_effect(Some(TearingMode::VBlank))
+        .unwrap();
 
-    let touch_interrupt_handler = TouchInterrupt {
-        interrupt_input: touch_interrupt,
-        interrupt_flag: false,
-    };
+    match spd2010.read_diagnostics() {
+        Ok(diagnostics) if diagnostics.is_healthy() => println!("Panel OK: {:?}", diagnostics.id),
+        Ok(diagnostics) => println!("Panel not healthy: {:?}", diagnostics),
+        Err(e) => println!("Couldn't read panel diagnostics: {:?}", e),
+    }
 
-    critical_section::with(|cs| {
-        TOUCH_INTERRUPT
-            .borrow_ref_mut(cs)
-            .replace(touch_interrupt_handler);
-    });
+    let config = InputConfig::default().with_pull(Pull::Up);
+    interrupt::install(Input::new(peripherals.GPIO4, config));
 
     Timer::after(Duration::from_millis(200)).await;
-    touch::reset(&mut Delay, &mut touch_reset_pin)
+    spd2010_touch::reset(&mut Delay, &mut touch_reset_pin)
         .await
         .unwrap();
     Timer::after(Duration::from_millis(200)).await;
-    let mut touch = SPD2010Touch::new(&mut i2c, &TOUCH_INTERRUPT);
+    let mut touch = Touch::new(&mut i2c, &TOUCH_EVENTS).with_filter(TouchFilter::default());
+    touch.set_orientation(spd2010.orientation());
     Timer::after(Duration::from_millis(200)).await;
 
-    println!("{}", touch.read_fw_version().unwrap());
+    println!("{}", touch.controller_mut().read_fw_version().unwrap());
 
     // let font = FontRenderer::new::<fonts::u8g2_font_logisoso92_tn>();
     // // let text = "Welcome to SteadyTickOS";
@@ -238,6 +217,7 @@ async fn main(_spawner: Spawner) {
 
     spd2010.fill();
     spd2010.flush().await.unwrap();
+    backlight.fade_to(128, Duration::from_millis(500)).await;
 
     // loop {
     //     let interrupt = touch_interrupt.is_interrupt_set();
@@ -256,35 +236,18 @@ async fn main(_spawner: Spawner) {
     // }
 
     loop {
-        let predicted_available = touch.available();
-        if !predicted_available {
+        if let Err(e) = touch.update().await {
+            println!("Touch: {:?}", e);
             continue;
         }
 
-        let mut touch_data = TouchData::default();
-        let new_data = touch.read(&mut Delay, &mut touch_data).await.unwrap();
-
-        for point in touch_data.points {
-            let circle = Circle::new(
-                Point {
-                    x: point.x as i32,
-                    y: point.y as i32,
-                },
-                5,
-            );
+        while let Ok(event) = TOUCH_EVENTS.try_receive() {
+            if event.phase == TouchPhase::Up {
+                continue;
+            }
+            let circle = Circle::new(event.position, 5);
             circle.draw_styled(&white, &mut spd2010).unwrap();
         }
-        spd2010.flush().await.unwrap();
+        spd2010.flush_dirty().await.unwrap();
     }
 }
-
-#[handler]
-fn handler() {
-    critical_section::with(|cs| {
-        TOUCH_INTERRUPT
-            .borrow_ref_mut(cs)
-            .as_mut()
-            .unwrap()
-            .possible_interrupt();
-    });
-}
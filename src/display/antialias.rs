@@ -0,0 +1,413 @@
+// Anti-aliased shapes for watch faces. Every shape is a signed distance
+// function sampled at pixel centres: pixels on the edge get partial coverage
+// and are blended with what's already in the framebuffer.
+//
+// Positions are `PointF`s in continuous coordinates, where pixel (x, y)
+// covers x..x + 1, so hands can rotate smoothly instead of snapping.
+
+use embedded_graphics::prelude::{Dimensions, Point};
+
+use super::{
+    config::DISPLAY_WIDTH,
+    draw::Spd2010,
+    pixel_format::PixelFormat,
+    transport::{QspiTransport, TearingEffect},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PointF {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl PointF {
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    /// Middle of the round screen, on the corner of the four centre pixels.
+    pub const fn center() -> Self {
+        let center = DISPLAY_WIDTH as f32 / 2.0;
+        Self::new(center, center)
+    }
+
+    /// Same as `round::polar` but without rounding to a pixel.
+    pub fn polar(angle_deg: f32, radius: f32) -> Self {
+        Self::center().offset_polar(angle_deg, radius)
+    }
+
+    /// Point at a watch angle and distance from this one.
+    pub fn offset_polar(self, angle_deg: f32, radius: f32) -> Self {
+        let radians = angle_deg.to_radians();
+        Self::new(
+            self.x + radius * libm::sinf(radians),
+            self.y - radius * libm::cosf(radians),
+        )
+    }
+
+    fn distance(self, other: PointF) -> f32 {
+        libm::hypotf(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl From<Point> for PointF {
+    /// Centre of the pixel.
+    fn from(point: Point) -> Self {
+        Self::new(point.x as f32 + 0.5, point.y as f32 + 0.5)
+    }
+}
+
+/// A watch hand pointing out from the centre, with round ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hand {
+    pub length: f32,
+    pub width: f32,
+    /// Width at the tip, for tapered hands
+    pub tip_width: f32,
+    /// How far it sticks out behind the centre
+    pub tail: f32,
+}
+
+impl Hand {
+    pub fn new(length: f32, width: f32) -> Self {
+        Self {
+            length,
+            width,
+            tip_width: width,
+            tail: 0.0,
+        }
+    }
+
+    pub fn with_tip_width(mut self, tip_width: f32) -> Self {
+        self.tip_width = tip_width;
+        self
+    }
+
+    pub fn with_tail(mut self, tail: f32) -> Self {
+        self.tail = tail;
+        self
+    }
+}
+
+// Horizontal extent of a shape on one row, which may have a hole in it
+struct RowSpan {
+    outer: (f32, f32),
+    hole: Option<(f32, f32)>,
+}
+
+// Distance from `p` to a segment whose radius goes from `ra` at `a` to `rb`
+// at `b`. Not exact for tapers, but close enough for hands.
+fn capsule(p: PointF, a: PointF, b: PointF, ra: f32, rb: f32) -> f32 {
+    let (pax, pay) = (p.x - a.x, p.y - a.y);
+    let (bax, bay) = (b.x - a.x, b.y - a.y);
+    let len2 = bax * bax + bay * bay;
+    let t = if len2 > 0.0 {
+        ((pax * bax + pay * bay) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    libm::hypotf(pax - bax * t, pay - bay * t) - (ra + (rb - ra) * t)
+}
+
+fn capsule_span(y: f32, a: PointF, b: PointF, reach: f32) -> Option<RowSpan> {
+    if y < a.y.min(b.y) - reach || y > a.y.max(b.y) + reach {
+        return None;
+    }
+    // Part of the segment within `reach` rows of this one
+    let (t0, t1) = if a.y == b.y {
+        (0.0, 1.0)
+    } else {
+        let t0 = ((y - reach - a.y) / (b.y - a.y)).clamp(0.0, 1.0);
+        let t1 = ((y + reach - a.y) / (b.y - a.y)).clamp(0.0, 1.0);
+        (t0, t1)
+    };
+    let x0 = a.x + (b.x - a.x) * t0;
+    let x1 = a.x + (b.x - a.x) * t1;
+    Some(RowSpan {
+        outer: (x0.min(x1) - reach, x0.max(x1) + reach),
+        hole: None,
+    })
+}
+
+// Rows of an annulus between `inner` and `outer` radius
+fn ring_span(y: f32, center: PointF, inner: f32, outer: f32) -> Option<RowSpan> {
+    let dy = (y - center.y).abs();
+    if dy > outer {
+        return None;
+    }
+    let half = |radius: f32| libm::sqrtf(radius * radius - dy * dy);
+    let outer_half = half(outer);
+    let hole = (inner > 0.0 && dy < inner).then(|| {
+        let inner_half = half(inner);
+        (center.x - inner_half, center.x + inner_half)
+    });
+    Some(RowSpan {
+        outer: (center.x - outer_half, center.x + outer_half),
+        hole,
+    })
+}
+
+// 0.0..360.0, without `rem_euclid` which core doesn't have for floats
+fn wrap_degrees(degrees: f32) -> f32 {
+    let wrapped = libm::fmodf(degrees, 360.0);
+    if wrapped < 0.0 {
+        wrapped + 360.0
+    } else {
+        wrapped
+    }
+}
+
+fn coverage(distance: f32) -> u8 {
+    ((0.5 - distance).clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+impl<B, T, C> Spd2010<B, T, C>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    /// Line with round caps, `width` 1.0 for a hairline.
+    pub fn draw_line_aa(&mut self, start: PointF, end: PointF, width: f32, color: C) {
+        let radius = width / 2.0;
+        let reach = radius + 1.0;
+        self.draw_sdf(
+            color,
+            (start.y.min(end.y) - reach, start.y.max(end.y) + reach),
+            |y| capsule_span(y, start, end, reach),
+            |p| capsule(p, start, end, radius, radius),
+        );
+    }
+
+    pub fn draw_hand_aa(&mut self, hand: &Hand, angle_deg: f32, color: C) {
+        let tail = PointF::polar(angle_deg + 180.0, hand.tail);
+        let tip = PointF::polar(angle_deg, hand.length);
+        let (tail_radius, tip_radius) = (hand.width / 2.0, hand.tip_width / 2.0);
+        let reach = tail_radius.max(tip_radius) + 1.0;
+        self.draw_sdf(
+            color,
+            (tail.y.min(tip.y) - reach, tail.y.max(tip.y) + reach),
+            |y| capsule_span(y, tail, tip, reach),
+            |p| capsule(p, tail, tip, tail_radius, tip_radius),
+        );
+    }
+
+    /// Circle outline, `stroke` wide and centred on `radius`.
+    pub fn draw_circle_aa(&mut self, center: PointF, radius: f32, stroke: f32, color: C) {
+        let half = stroke / 2.0;
+        let (inner, outer) = (radius - half - 1.0, radius + half + 1.0);
+        self.draw_sdf(
+            color,
+            (center.y - outer, center.y + outer),
+            |y| ring_span(y, center, inner, outer),
+            |p| (p.distance(center) - radius).abs() - half,
+        );
+    }
+
+    pub fn fill_circle_aa(&mut self, center: PointF, radius: f32, color: C) {
+        let outer = radius + 1.0;
+        self.draw_sdf(
+            color,
+            (center.y - outer, center.y + outer),
+            |y| ring_span(y, center, 0.0, outer),
+            |p| p.distance(center) - radius,
+        );
+    }
+
+    /// Arc with round caps. Angles are watch angles like `round::arc`:
+    /// 0.0 is 12 o'clock, positive sweeps go clockwise.
+    pub fn draw_arc_aa(
+        &mut self,
+        center: PointF,
+        radius: f32,
+        start_deg: f32,
+        sweep_deg: f32,
+        stroke: f32,
+        color: C,
+    ) {
+        let (start_deg, sweep_deg) = if sweep_deg < 0.0 {
+            (start_deg + sweep_deg, -sweep_deg)
+        } else {
+            (start_deg, sweep_deg)
+        };
+        if sweep_deg >= 360.0 {
+            return self.draw_circle_aa(center, radius, stroke, color);
+        }
+
+        let cap_start = center.offset_polar(start_deg, radius);
+        let cap_end = center.offset_polar(start_deg + sweep_deg, radius);
+
+        let half = stroke / 2.0;
+        let (inner, outer) = (radius - half - 1.0, radius + half + 1.0);
+        self.draw_sdf(
+            color,
+            (center.y - outer, center.y + outer),
+            |y| ring_span(y, center, inner, outer),
+            |p| {
+                let angle = libm::atan2f(p.x - center.x, center.y - p.y).to_degrees();
+                if wrap_degrees(angle - start_deg) <= sweep_deg {
+                    (p.distance(center) - radius).abs() - half
+                } else {
+                    p.distance(cap_start).min(p.distance(cap_end)) - half
+                }
+            },
+        );
+    }
+
+    // Samples `distance` at every pixel centre in `rows` that `span` says
+    // might be covered and blends `color` in by coverage, a row at a time
+    fn draw_sdf(
+        &mut self,
+        color: C,
+        rows: (f32, f32),
+        span: impl Fn(f32) -> Option<RowSpan>,
+        distance: impl Fn(PointF) -> f32,
+    ) {
+        let max_y = self.bounding_box().size.height as i32 - 1;
+        let to_pixel = |v: f32| libm::floorf(v) as i32;
+
+        for y in to_pixel(rows.0).max(0)..=to_pixel(rows.1).min(max_y) {
+            let center_y = y as f32 + 0.5;
+            let Some(RowSpan { outer, hole }) = span(center_y) else {
+                continue;
+            };
+
+            self.blend_row(y, to_pixel(outer.0), to_pixel(outer.1), color, |x| {
+                let center_x = x as f32 + 0.5;
+                if hole.is_some_and(|(x1, x2)| center_x > x1 && center_x < x2) {
+                    return 0;
+                }
+                coverage(distance(PointF::new(center_x, center_y)))
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+
+    use super::*;
+    use crate::display::{
+        mock::{NoTearing, RecordingBus, display},
+        orientation::{Orientation, Rotation},
+    };
+
+    // White over black blends to the coverage itself
+    fn coverage_at(display: &Spd2010<RecordingBus, NoTearing, Rgb888>, x: i32, y: i32) -> u8 {
+        display.pixel(Point::new(x, y)).unwrap().r()
+    }
+
+    #[test]
+    fn coverage_follows_distance() {
+        assert_eq!(coverage(-1.0), 255);
+        assert_eq!(coverage(-0.5), 255);
+        assert_eq!(coverage(-0.25), 191);
+        assert_eq!(coverage(0.0), 128);
+        assert_eq!(coverage(0.25), 64);
+        assert_eq!(coverage(0.5), 0);
+        assert_eq!(coverage(2.0), 0);
+    }
+
+    #[test]
+    fn wraps_degrees() {
+        assert_eq!(wrap_degrees(0.0), 0.0);
+        assert_eq!(wrap_degrees(-90.0), 270.0);
+        assert_eq!(wrap_degrees(725.0), 5.0);
+        assert_eq!(wrap_degrees(-720.0), 0.0);
+    }
+
+    #[test]
+    fn line_edges_are_partially_covered() {
+        for rotation in [
+            Rotation::Deg0,
+            Rotation::Deg90,
+            Rotation::Deg180,
+            Rotation::Deg270,
+        ] {
+            let mut display = display();
+            display.set_orientation(Orientation::new(rotation)).unwrap();
+            let (start, end) = (PointF::new(100.0, 100.0), PointF::new(120.0, 100.0));
+            display.draw_line_aa(start, end, 2.5, Rgb888::WHITE);
+
+            // Rows 1.5 pixels from the line are a quarter pixel inside it
+            let column: [u8; 5] =
+                core::array::from_fn(|i| coverage_at(&display, 110, 98 + i as i32));
+            assert_eq!(column, [64, 255, 255, 64, 0], "{rotation:?}");
+            assert_eq!(coverage_at(&display, 110, 97), 0);
+        }
+    }
+
+    #[test]
+    fn blends_over_the_background() {
+        let mut display = display();
+        let Ok(()) = display.clear(Rgb888::new(0, 0, 200));
+        display.draw_line_aa(
+            PointF::new(100.0, 100.0),
+            PointF::new(120.0, 100.0),
+            2.5,
+            Rgb888::WHITE,
+        );
+
+        assert_eq!(display.pixel(Point::new(110, 99)), Some(Rgb888::WHITE));
+        // 64 / 255 white over blue
+        assert_eq!(
+            display.pixel(Point::new(110, 98)),
+            Some(Rgb888::new(64, 64, 214))
+        );
+    }
+
+    // Share of the pixel inside the circle, from a 16 x 16 grid of samples
+    fn sampled_area(x: i32, y: i32, center: PointF, radius: f32) -> f32 {
+        let inside = (0..16 * 16)
+            .filter(|i| {
+                let dx = x as f32 + (i % 16) as f32 / 16.0 + 1.0 / 32.0 - center.x;
+                let dy = y as f32 + (i / 16) as f32 / 16.0 + 1.0 / 32.0 - center.y;
+                dx * dx + dy * dy <= radius * radius
+            })
+            .count();
+        inside as f32 / 256.0
+    }
+
+    #[test]
+    fn filled_circle_matches_reference() {
+        let mut display = display();
+        display.fill_circle_aa(PointF::center(), 10.0, Rgb888::WHITE);
+
+        // Pixel centres at 9.5, 10.0 and 10.5 from the centre, straight right
+        assert_eq!(coverage_at(&display, 215, 206), 252);
+        assert_eq!(coverage_at(&display, 206, 206), 255);
+        assert_eq!(coverage_at(&display, 216, 206), 0);
+        // 7.5 right and 6.5 down, 9.92 away
+        assert_eq!(coverage_at(&display, 213, 212), 147);
+
+        // Every pixel within a few percent of the area actually covered, and
+        // the whole disc adds up to pi * r^2
+        let mut total = 0.0;
+        for y in 194..218 {
+            for x in 194..218 {
+                let area = sampled_area(x, y, PointF::center(), 10.0);
+                let coverage = coverage_at(&display, x, y) as f32 / 255.0;
+                assert!(
+                    (coverage - area).abs() < 0.06,
+                    "({x}, {y}) {coverage} {area}"
+                );
+                total += coverage;
+            }
+        }
+        let disc = core::f32::consts::PI * 100.0;
+        assert!((total - disc).abs() < disc * 0.01, "{total}");
+        assert!(!display.dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn arc_covers_only_its_sweep() {
+        let mut display = display();
+        // Across 12 o'clock, so the sweep wraps through 0
+        display.draw_arc_aa(PointF::center(), 100.0, 350.0, 20.0, 4.0, Rgb888::WHITE);
+
+        assert_eq!(coverage_at(&display, 206, 106), 255);
+        assert_eq!(coverage_at(&display, 206, 306), 0);
+        assert_eq!(coverage_at(&display, 306, 206), 0);
+    }
+}
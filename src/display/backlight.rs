@@ -0,0 +1,121 @@
+use embassy_time::{Duration, Instant, Timer};
+use esp_hal::{
+    gpio::interconnect::PeripheralOutput,
+    ledc::{
+        LSGlobalClkSource, Ledc, LowSpeed,
+        channel::{self, Channel, ChannelHW, ChannelIFace},
+        timer::{self, TimerIFace},
+    },
+    time::Rate,
+};
+
+use super::{
+    draw::Spd2010,
+    pixel_format::PixelFormat,
+    transport::{QspiTransport, TearingEffect},
+};
+
+const MAX_DUTY: u32 = (1 << 13) - 1; // Duty13Bit
+const GAMMA: f32 = 2.2;
+const FADE_STEP: Duration = Duration::from_millis(10);
+
+/// Sets up the LEDC timer the backlight channel runs from. It has to outlive
+/// the [`Backlight`].
+pub fn timer<'a>(ledc: &mut Ledc<'a>) -> timer::Timer<'a, LowSpeed> {
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+    let mut backlight_timer = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    backlight_timer
+        .configure(timer::config::Config {
+            duty: timer::config::Duty::Duty13Bit,
+            clock_source: timer::LSClockSource::APBClk,
+            frequency: Rate::from_khz(5),
+        })
+        .unwrap();
+
+    backlight_timer
+}
+
+/// Maps a perceived brightness (0.0 - 255.0) to a PWM duty.
+pub fn gamma_duty(level: f32) -> u32 {
+    let linear = libm::powf(level.clamp(0.0, 255.0) / 255.0, GAMMA);
+    (linear * MAX_DUTY as f32 + 0.5) as u32
+}
+
+/// Backlight PWM with perceptual brightness levels.
+pub struct Backlight<'a> {
+    channel: Channel<'a, LowSpeed>,
+    level: u8,
+}
+
+impl<'a> Backlight<'a> {
+    pub fn new(
+        ledc: &Ledc<'a>,
+        timer: &'a timer::Timer<'a, LowSpeed>,
+        pin: impl PeripheralOutput<'a>,
+        level: u8,
+    ) -> Self {
+        let mut channel = ledc.channel(channel::Number::Channel0, pin);
+        channel
+            .configure(channel::config::Config {
+                timer,
+                duty_pct: 0,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .unwrap();
+
+        let mut backlight = Self { channel, level };
+        backlight.set_brightness(level);
+        backlight
+    }
+
+    pub fn brightness(&self) -> u8 {
+        self.level
+    }
+
+    pub fn set_brightness(&mut self, level: u8) {
+        self.channel.set_duty_hw(gamma_duty(level as f32));
+        self.level = level;
+    }
+
+    /// Same as [`Self::set_brightness`], and also writes the level to the
+    /// panel's WRDISBV register.
+    pub fn set_brightness_mirrored<B, T, C>(
+        &mut self,
+        level: u8,
+        display: &mut Spd2010<B, T, C>,
+    ) -> Result<(), B::Error>
+    where
+        B: QspiTransport,
+        T: TearingEffect,
+        C: PixelFormat,
+    {
+        self.set_brightness(level);
+        display.set_display_brightness(level)
+    }
+
+    /// Fades evenly in perceived brightness, returns once `level` is reached.
+    pub async fn fade_to(&mut self, level: u8, duration: Duration) {
+        let start_level = self.level as f32;
+        let delta = level as f32 - start_level;
+        let start = Instant::now();
+
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= duration {
+                break;
+            }
+
+            let progress = elapsed.as_micros() as f32 / duration.as_micros() as f32;
+            self.channel.set_duty_hw(gamma_duty(start_level + delta * progress));
+            Timer::after(FADE_STEP).await;
+        }
+
+        self.set_brightness(level);
+    }
+
+    /// Wakes the screen straight to full brightness.
+    pub fn wake(&mut self) {
+        self.set_brightness(u8::MAX);
+    }
+}
@@ -0,0 +1,319 @@
+// Colour transforms applied to pixels on their way to the panel, so the
+// framebuffer keeps the colours as drawn (and blending keeps working).
+// Everything per channel is folded into one lookup table per channel.
+
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+
+use super::pixel_format::PixelFormat;
+
+/// Colour temperature that leaves white alone.
+pub const NEUTRAL_TEMPERATURE: u32 = 6600;
+
+// What's left of green and blue at full night mode strength
+const NIGHT_GAINS: [f32; 3] = [1.0, 0.45, 0.1];
+
+/// The panel's built-in gamma curves, selected with GAMSET.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GammaCurve {
+    Curve1 = 0x01,
+    Curve2 = 0x02,
+    Curve3 = 0x04,
+    Curve4 = 0x08,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Normal,
+    /// Red shift, 0 is none and 255 leaves mostly red
+    Night(u8),
+    Greyscale,
+    /// Steeper contrast around mid grey
+    HighContrast,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorPipeline {
+    gamma: f32,
+    temperature: u32,
+    mode: ColorMode,
+    lut: [[u8; 256]; 3],
+}
+
+impl ColorPipeline {
+    pub fn new() -> Self {
+        let mut pipeline = Self {
+            gamma: 1.0,
+            temperature: NEUTRAL_TEMPERATURE,
+            mode: ColorMode::Normal,
+            lut: [[0; 256]; 3],
+        };
+        pipeline.rebuild();
+        pipeline
+    }
+
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+
+    /// Output is input to the power of `gamma`, above 1.0 darkens the mid
+    /// tones.
+    pub fn with_gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma.max(0.1);
+        self.rebuild();
+        self
+    }
+
+    pub fn temperature(&self) -> u32 {
+        self.temperature
+    }
+
+    /// White point in kelvin, lower is warmer. Only ever takes away from a
+    /// channel, never boosts one.
+    pub fn with_temperature(mut self, kelvin: u32) -> Self {
+        self.temperature = kelvin.clamp(1000, 40000);
+        self.rebuild();
+        self
+    }
+
+    pub fn mode(&self) -> ColorMode {
+        self.mode
+    }
+
+    pub fn with_mode(mut self, mode: ColorMode) -> Self {
+        self.mode = mode;
+        self.rebuild();
+        self
+    }
+
+    /// Nothing to do, pixels can go out untouched.
+    pub fn is_identity(&self) -> bool {
+        self.gamma == 1.0
+            && self.temperature == NEUTRAL_TEMPERATURE
+            && self.mode == ColorMode::Normal
+    }
+
+    pub fn transform(&self, color: Rgb888) -> Rgb888 {
+        let (r, g, b) = match self.mode {
+            ColorMode::Greyscale => {
+                let [r, g, b] = [color.r(), color.g(), color.b()].map(|c| c as u32);
+                let luma = ((77 * r + 150 * g + 29 * b) >> 8) as u8;
+                (luma, luma, luma)
+            }
+            _ => (color.r(), color.g(), color.b()),
+        };
+        Rgb888::new(
+            self.lut[0][r as usize],
+            self.lut[1][g as usize],
+            self.lut[2][b as usize],
+        )
+    }
+
+    /// Transforms framebuffer bytes in place.
+    pub fn apply<C: PixelFormat>(&self, bytes: &mut [u8]) {
+        for pixel in bytes.chunks_exact_mut(C::BYTES) {
+            let color: Rgb888 = C::from_bytes(pixel).into();
+            C::from(self.transform(color)).write_bytes(pixel);
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let white = white_point(self.temperature);
+        let night = match self.mode {
+            ColorMode::Night(strength) => strength as f32 / 255.0,
+            _ => 0.0,
+        };
+
+        for (channel, lut) in self.lut.iter_mut().enumerate() {
+            let gain = white[channel] * (1.0 + (NIGHT_GAINS[channel] - 1.0) * night);
+            for (value, out) in lut.iter_mut().enumerate() {
+                let mut x = value as f32 / 255.0;
+                if self.mode == ColorMode::HighContrast {
+                    x = ((x - 0.5) * 2.0 + 0.5).clamp(0.0, 1.0);
+                }
+                x = libm::powf(x, self.gamma) * gain;
+                *out = (x * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+impl Default for ColorPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Relative channel gains of a black body at `kelvin`, after Tanner Helland's
+// fit. All 1.0 at `NEUTRAL_TEMPERATURE`.
+fn white_point(kelvin: u32) -> [f32; 3] {
+    let t = kelvin as f32 / 100.0;
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.69873 * libm::powf(t - 60.0, -0.13320476)
+    };
+    let g = if t <= 66.0 {
+        99.4708 * libm::logf(t) - 161.11957
+    } else {
+        288.12216 * libm::powf(t - 60.0, -0.07551485)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * libm::logf(t - 10.0) - 305.0448
+    };
+    [r, g, b].map(|c| (c / 255.0).clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        prelude::{DrawTarget, Point, Size},
+        primitives::Rectangle,
+    };
+
+    use super::*;
+    use crate::display::{lcd_command, mock};
+
+    fn lut(pipeline: &ColorPipeline, channel: usize, values: [u8; 5]) -> [u8; 5] {
+        values.map(|v| pipeline.lut[channel][v as usize])
+    }
+
+    #[test]
+    fn default_is_identity() {
+        let pipeline = ColorPipeline::new();
+        assert!(pipeline.is_identity());
+        for channel in pipeline.lut {
+            assert!(channel.iter().enumerate().all(|(i, &v)| v == i as u8));
+        }
+
+        assert!(ColorPipeline::new().with_gamma(1.0).is_identity());
+        let neutral = ColorPipeline::new().with_temperature(NEUTRAL_TEMPERATURE);
+        assert!(neutral.is_identity());
+        assert!(!ColorPipeline::new().with_gamma(2.2).is_identity());
+        assert!(!ColorPipeline::new().with_temperature(3000).is_identity());
+        assert!(
+            !ColorPipeline::new()
+                .with_mode(ColorMode::Greyscale)
+                .is_identity()
+        );
+    }
+
+    #[test]
+    fn gamma_bends_the_mid_tones() {
+        let values = [0, 64, 128, 192, 255];
+        let pipeline = ColorPipeline::new().with_gamma(2.0);
+        // (v / 255)^2 * 255, rounded
+        for channel in 0..3 {
+            assert_eq!(lut(&pipeline, channel, values), [0, 16, 64, 145, 255]);
+        }
+        let pipeline = ColorPipeline::new().with_gamma(0.5);
+        assert_eq!(lut(&pipeline, 0, values), [0, 128, 181, 221, 255]);
+        // Clamped rather than flattening everything to white
+        assert_eq!(ColorPipeline::new().with_gamma(0.0).gamma(), 0.1);
+    }
+
+    #[test]
+    fn temperature_only_takes_away() {
+        assert_eq!(white_point(NEUTRAL_TEMPERATURE), [1.0; 3]);
+
+        let warm = ColorPipeline::new().with_temperature(3000);
+        assert_eq!(warm.transform(Rgb888::WHITE), Rgb888::new(255, 177, 110));
+        assert_eq!(warm.transform(Rgb888::BLACK), Rgb888::BLACK);
+
+        let cool = ColorPipeline::new().with_temperature(10000);
+        let white = cool.transform(Rgb888::WHITE);
+        assert!(white.r() < white.g() && white.g() < white.b());
+        assert_eq!(white.b(), 255);
+
+        assert_eq!(
+            ColorPipeline::new().with_temperature(100).temperature(),
+            1000
+        );
+    }
+
+    #[test]
+    fn night_mode_shifts_to_red() {
+        let night = ColorPipeline::new().with_mode(ColorMode::Night(255));
+        assert_eq!(night.transform(Rgb888::WHITE), Rgb888::new(255, 115, 26));
+
+        // Half strength goes half way
+        let half = ColorPipeline::new().with_mode(ColorMode::Night(128));
+        assert_eq!(half.transform(Rgb888::WHITE), Rgb888::new(255, 185, 140));
+
+        let none = ColorPipeline::new().with_mode(ColorMode::Night(0));
+        assert_eq!(none.lut, ColorPipeline::new().lut);
+    }
+
+    #[test]
+    fn greyscale_uses_luma() {
+        let grey = ColorPipeline::new().with_mode(ColorMode::Greyscale);
+        assert_eq!(grey.transform(Rgb888::RED), Rgb888::new(76, 76, 76));
+        assert_eq!(grey.transform(Rgb888::GREEN), Rgb888::new(149, 149, 149));
+        assert_eq!(grey.transform(Rgb888::BLUE), Rgb888::new(28, 28, 28));
+        assert_eq!(grey.transform(Rgb888::WHITE), Rgb888::WHITE);
+    }
+
+    #[test]
+    fn high_contrast_doubles_the_slope() {
+        let contrast = ColorPipeline::new().with_mode(ColorMode::HighContrast);
+        assert_eq!(
+            lut(&contrast, 1, [0, 64, 128, 160, 192]),
+            [0, 1, 129, 193, 255]
+        );
+    }
+
+    #[test]
+    fn applies_to_framebuffer_bytes() {
+        let night = ColorPipeline::new().with_mode(ColorMode::Night(255));
+        let mut bytes = [255, 255, 255, 10, 20, 30];
+        night.apply::<Rgb888>(&mut bytes);
+        assert_eq!(bytes, [255, 115, 26, 10, 9, 3]);
+    }
+
+    #[test]
+    fn gamma_curve_is_one_gamset() {
+        let mut display = mock::display::<Rgb888>();
+        display.set_gamma_curve(GammaCurve::Curve3).unwrap();
+        let sent: Vec<_> = display
+            .transport()
+            .commands()
+            .map(|t| (t.cmd, t.data.clone()))
+            .collect();
+        assert_eq!(sent, [(lcd_command::GAMSET, [0x04].to_vec())]);
+    }
+
+    #[test]
+    fn flush_sends_transformed_pixels() {
+        let mut display = mock::display::<Rgb888>();
+        block_on(display.init()).unwrap();
+        display.set_color_pipeline(ColorPipeline::new().with_mode(ColorMode::Night(255)));
+        block_on(display.flush()).unwrap();
+
+        let area = Rectangle::new(Point::new(200, 200), Size::new(4, 1));
+        let Ok(()) = display.fill_solid(&area, Rgb888::WHITE);
+        display.transport_mut().clear();
+        block_on(display.flush_dirty()).unwrap();
+
+        let bus = display.transport();
+        let pixels: Vec<u8> = bus
+            .transfers
+            .iter()
+            .filter(|t| t.is_pixels())
+            .flat_map(|t| t.data.iter().copied())
+            .collect();
+        assert_eq!(pixels, [255, 115, 26].repeat(4));
+        // The framebuffer keeps what was drawn
+        assert_eq!(display.pixel(area.top_left), Some(Rgb888::WHITE));
+
+        // An identity pipeline is dropped, pixels go out as they are
+        display.set_color_pipeline(ColorPipeline::new());
+        assert!(display.color_pipeline().is_none());
+    }
+}
@@ -0,0 +1,455 @@
+// Off-screen RGBA layers blended into the framebuffer, e.g. a static watch
+// face with a toast on top. Layers track what changed in screen coordinates,
+// so `Compositor::compose` only recomposes those areas; flush as usual after.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::convert::Infallible;
+
+use embedded_graphics::{
+    Pixel,
+    pixelcolor::{Rgb888, RgbColor},
+    prelude::{DrawTarget, OriginDimensions, Point, Size, Transform},
+    primitives::Rectangle,
+};
+
+use super::{
+    dirty::DirtyRegions,
+    draw::Spd2010,
+    pixel_format::{PixelFormat, blend},
+    transport::{QspiTransport, TearingEffect},
+};
+
+const TRANSPARENT: [u8; 4] = [0; 4];
+
+/// An RGBA buffer positioned on the screen. Starts out fully transparent;
+/// anything drawn through `DrawTarget` is opaque.
+pub struct Layer {
+    pixels: Box<[u8]>,
+    size: Size,
+    position: Point,
+    opacity: u8,
+    visible: bool,
+    // In screen (UI) coordinates
+    dirty: DirtyRegions,
+}
+
+impl Layer {
+    pub fn new(size: Size, position: Point) -> Self {
+        let len = (size.width * size.height) as usize * 4;
+        let pixels = unsafe { Box::<[u8]>::new_zeroed_slice(len).assume_init() };
+
+        Self {
+            pixels,
+            size,
+            position,
+            opacity: 0xFF,
+            visible: true,
+            dirty: DirtyRegions::unaligned(),
+        }
+    }
+
+    /// Where the layer is on screen.
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(self.position, self.size)
+    }
+
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Point) {
+        if position != self.position {
+            self.dirty.add(self.bounds());
+            self.position = position;
+            self.dirty.add(self.bounds());
+        }
+    }
+
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    /// Multiplies the alpha of every pixel, 0 hides the layer.
+    pub fn set_opacity(&mut self, opacity: u8) {
+        if opacity != self.opacity {
+            self.opacity = opacity;
+            self.dirty.add(self.bounds());
+        }
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible != self.visible {
+            self.visible = visible;
+            self.dirty.add(self.bounds());
+        }
+    }
+
+    /// Sets one pixel with its own alpha, in layer coordinates.
+    pub fn set_pixel(&mut self, point: Point, color: Rgb888, alpha: u8) {
+        if let Some(index) = self.index(point) {
+            self.pixels[index..index + 4].copy_from_slice(&[
+                color.r(),
+                color.g(),
+                color.b(),
+                alpha,
+            ]);
+            self.mark_dirty(Rectangle::new(point, Size::new(1, 1)));
+        }
+    }
+
+    /// Makes `area` (layer coordinates) transparent again.
+    pub fn erase(&mut self, area: &Rectangle) {
+        self.fill_rgba(area, TRANSPARENT);
+    }
+
+    pub fn erase_all(&mut self) {
+        self.pixels.fill(0);
+        self.dirty.add(self.bounds());
+    }
+
+    // RGBA at a screen position, None outside the layer
+    fn screen_pixel(&self, point: Point) -> Option<[u8; 4]> {
+        let index = self.index(point - self.position)?;
+        let pixel = &self.pixels[index..index + 4];
+        Some([pixel[0], pixel[1], pixel[2], pixel[3]])
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        let (x, y): (u32, u32) = point.try_into().ok()?;
+        (x < self.size.width && y < self.size.height)
+            .then(|| (y * self.size.width + x) as usize * 4)
+    }
+
+    fn fill_rgba(&mut self, area: &Rectangle, rgba: [u8; 4]) {
+        let area = area.intersection(&Rectangle::new(Point::zero(), self.size));
+        if area.is_zero_sized() {
+            return;
+        }
+
+        let stride = self.size.width as usize * 4;
+        for y in area.rows() {
+            let start = y as usize * stride + area.top_left.x as usize * 4;
+            let end = start + area.size.width as usize * 4;
+            for pixel in self.pixels[start..end].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&rgba);
+            }
+        }
+        self.mark_dirty(area);
+    }
+
+    fn mark_dirty(&mut self, area: Rectangle) {
+        self.dirty.add(area.translate(self.position));
+    }
+}
+
+impl DrawTarget for Layer {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let mut min = Point::new(i32::MAX, i32::MAX);
+        let mut max = Point::new(i32::MIN, i32::MIN);
+
+        for Pixel(point, color) in pixels {
+            if let Some(index) = self.index(point) {
+                self.pixels[index..index + 4].copy_from_slice(&[
+                    color.r(),
+                    color.g(),
+                    color.b(),
+                    0xFF,
+                ]);
+                min = min.component_min(point);
+                max = max.component_max(point);
+            }
+        }
+
+        if min.x <= max.x {
+            self.mark_dirty(Rectangle::with_corners(min, max));
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_rgba(area, [color.r(), color.g(), color.b(), 0xFF]);
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid(&Rectangle::new(Point::zero(), self.size), color)
+    }
+}
+
+impl OriginDimensions for Layer {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+/// Stack of layers, bottom first, over a solid background.
+pub struct Compositor {
+    layers: Vec<Layer>,
+    background: Rgb888,
+    // Damage that isn't any one layer's, e.g. a removed layer
+    dirty: DirtyRegions,
+}
+
+impl Compositor {
+    pub fn new(background: Rgb888) -> Self {
+        let mut dirty = DirtyRegions::unaligned();
+        dirty.mark_all();
+        Self {
+            layers: Vec::new(),
+            background,
+            dirty,
+        }
+    }
+
+    /// Adds a layer on top, returns its index.
+    pub fn push(&mut self, layer: Layer) -> usize {
+        self.dirty.add(layer.bounds());
+        self.layers.push(layer);
+        self.layers.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Layer {
+        let layer = self.layers.remove(index);
+        self.dirty.add(layer.bounds());
+        layer
+    }
+
+    pub fn layer(&self, index: usize) -> &Layer {
+        &self.layers[index]
+    }
+
+    pub fn layer_mut(&mut self, index: usize) -> &mut Layer {
+        &mut self.layers[index]
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn set_background(&mut self, background: Rgb888) {
+        self.background = background;
+        self.dirty.mark_all();
+    }
+
+    /// Blends every layer into `display` wherever something changed since
+    /// the last call.
+    pub fn compose<B, T, C>(&mut self, display: &mut Spd2010<B, T, C>)
+    where
+        B: QspiTransport,
+        T: TearingEffect,
+        C: PixelFormat,
+    {
+        let mut damage = core::mem::replace(&mut self.dirty, DirtyRegions::unaligned());
+        for layer in self.layers.iter_mut() {
+            for area in layer.dirty.take() {
+                damage.add(area);
+            }
+        }
+
+        let mut row: Vec<C> = Vec::new();
+        for area in damage.iter() {
+            // Only the layers that can show up in this area
+            let layers: Vec<&Layer> = self
+                .layers
+                .iter()
+                .filter(|layer| layer.visible && layer.opacity > 0)
+                .filter(|layer| !layer.bounds().intersection(area).is_zero_sized())
+                .collect();
+
+            for y in area.rows() {
+                for x in area.columns() {
+                    let point = Point::new(x, y);
+                    let mut color = self.background;
+                    for layer in layers.iter() {
+                        if let Some([r, g, b, a]) = layer.screen_pixel(point) {
+                            let alpha = (a as u32 * layer.opacity as u32 / 255) as u8;
+                            color = blend(color, Rgb888::new(r, g, b), alpha);
+                        }
+                    }
+                    row.push(color.into());
+                }
+
+                let row_area = Rectangle::new(
+                    Point::new(area.top_left.x, y),
+                    Size::new(area.size.width, 1),
+                );
+                let Ok(()) = display.fill_contiguous(&row_area, row.drain(..));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use super::*;
+    use crate::display::mock::{self, NoTearing, RecordingBus};
+
+    type Display = Spd2010<RecordingBus, NoTearing, Rgb888>;
+
+    const BACKGROUND: Rgb888 = Rgb888::new(100, 100, 100);
+
+    fn at(display: &Display, x: i32, y: i32) -> Rgb888 {
+        display.pixel(Point::new(x, y)).unwrap()
+    }
+
+    fn regions(dirty: &DirtyRegions) -> Vec<Rectangle> {
+        dirty.iter().copied().collect()
+    }
+
+    fn square(x: i32, y: i32, side: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new_equal(side))
+    }
+
+    // Composed once and flushed, so only new damage shows up on the bus
+    fn composed(compositor: &mut Compositor) -> Display {
+        let mut display = mock::display();
+        block_on(display.init()).unwrap();
+        compositor.compose(&mut display);
+        block_on(display.flush_dirty()).unwrap();
+        display.transport_mut().clear();
+        display
+    }
+
+    #[test]
+    fn moving_a_layer_damages_where_it_was_and_is() {
+        let mut layer = Layer::new(Size::new(10, 6), Point::new(10, 10));
+        assert!(layer.dirty.is_empty());
+
+        layer.set_position(Point::new(31, 10));
+        assert_eq!(
+            regions(&layer.dirty),
+            [
+                Rectangle::new(Point::new(10, 10), Size::new(10, 6)),
+                Rectangle::new(Point::new(31, 10), Size::new(10, 6)),
+            ]
+        );
+
+        // Not moving isn't damage
+        layer.dirty.clear();
+        layer.set_position(Point::new(31, 10));
+        assert!(layer.dirty.is_empty());
+    }
+
+    #[test]
+    fn layer_damage_is_not_column_aligned() {
+        let mut layer = Layer::new(Size::new(8, 8), Point::new(101, 50));
+        layer.set_pixel(Point::new(1, 2), Rgb888::RED, 0xFF);
+        assert_eq!(regions(&layer.dirty), [square(102, 52, 1)]);
+
+        let Ok(()) = layer.fill_solid(&square(4, 4, 2), Rgb888::RED);
+        assert_eq!(
+            regions(&layer.dirty),
+            [square(102, 52, 1), square(105, 54, 2)]
+        );
+    }
+
+    #[test]
+    fn blends_alpha_and_opacity() {
+        let mut layer = Layer::new(Size::new(4, 1), Point::new(200, 200));
+        layer.set_pixel(Point::new(0, 0), Rgb888::WHITE, 0xFF);
+        layer.set_pixel(Point::new(1, 0), Rgb888::WHITE, 0x80);
+        // (2, 0) stays transparent
+
+        let mut compositor = Compositor::new(BACKGROUND);
+        compositor.push(layer);
+        let mut display = composed(&mut compositor);
+        // (255 * 128 + 100 * 127 + 127) / 255
+        assert_eq!(at(&display, 200, 200), Rgb888::WHITE);
+        assert_eq!(at(&display, 201, 200), Rgb888::new(178, 178, 178));
+        assert_eq!(at(&display, 202, 200), BACKGROUND);
+
+        // Half opacity halves every alpha, 0x80 becomes 64
+        compositor.layer_mut(0).set_opacity(0x80);
+        compositor.compose(&mut display);
+        assert_eq!(at(&display, 200, 200), Rgb888::new(178, 178, 178));
+        assert_eq!(at(&display, 201, 200), Rgb888::new(139, 139, 139));
+
+        compositor.layer_mut(0).set_opacity(0);
+        compositor.compose(&mut display);
+        assert_eq!(at(&display, 200, 200), BACKGROUND);
+        assert_eq!(at(&display, 201, 200), BACKGROUND);
+    }
+
+    #[test]
+    fn later_layers_go_on_top() {
+        let mut bottom = Layer::new(Size::new(10, 10), Point::new(100, 100));
+        let Ok(()) = bottom.clear(Rgb888::RED);
+        let mut top = Layer::new(Size::new(10, 10), Point::new(105, 100));
+        let Ok(()) = top.clear(Rgb888::BLUE);
+
+        let mut compositor = Compositor::new(BACKGROUND);
+        compositor.push(bottom);
+        let top = compositor.push(top);
+        let mut display = composed(&mut compositor);
+        assert_eq!(at(&display, 104, 100), Rgb888::RED);
+        assert_eq!(at(&display, 105, 100), Rgb888::BLUE);
+        assert_eq!(at(&display, 114, 100), Rgb888::BLUE);
+        assert_eq!(at(&display, 115, 100), BACKGROUND);
+
+        // The top layer half see-through over the bottom one
+        compositor.layer_mut(top).set_opacity(0x80);
+        compositor.compose(&mut display);
+        assert_eq!(at(&display, 105, 100), Rgb888::new(127, 0, 128));
+        assert_eq!(at(&display, 114, 100), Rgb888::new(50, 50, 178));
+
+        // Hiding or removing it shows what's underneath again
+        compositor.layer_mut(top).set_visible(false);
+        compositor.compose(&mut display);
+        assert_eq!(at(&display, 105, 100), Rgb888::RED);
+        compositor.layer_mut(top).set_visible(true);
+        compositor.remove(top);
+        compositor.compose(&mut display);
+        assert_eq!(at(&display, 105, 100), Rgb888::RED);
+        assert_eq!(at(&display, 114, 100), BACKGROUND);
+    }
+
+    #[test]
+    fn recomposes_only_the_damage() {
+        let mut layer = Layer::new(Size::new(40, 40), Point::new(100, 100));
+        let Ok(()) = layer.clear(Rgb888::RED);
+        let mut compositor = Compositor::new(BACKGROUND);
+        compositor.push(layer);
+        let mut display = composed(&mut compositor);
+
+        // Drawn straight into the framebuffer, so recomposing would undo it
+        let outside = square(120, 120, 4);
+        let Ok(()) = display.fill_solid(&outside, Rgb888::GREEN);
+        block_on(display.flush_dirty()).unwrap();
+        display.transport_mut().clear();
+
+        compositor
+            .layer_mut(0)
+            .set_pixel(Point::new(2, 3), Rgb888::BLUE, 0xFF);
+        compositor.compose(&mut display);
+        assert_eq!(at(&display, 102, 103), Rgb888::BLUE);
+        assert_eq!(at(&display, 120, 120), Rgb888::GREEN);
+
+        // One pixel of damage, widened to the panel's columns on the way out
+        block_on(display.flush_dirty()).unwrap();
+        let window = Rectangle::new(Point::new(100, 103), Size::new(4, 1));
+        assert_eq!(display.transport().windows(), [window]);
+
+        // Nothing changed, nothing sent
+        display.transport_mut().clear();
+        compositor.compose(&mut display);
+        block_on(display.flush_dirty()).unwrap();
+        assert!(display.transport().transfers.is_empty());
+    }
+}
@@ -2,9 +2,9 @@ pub const DISPLAY_WIDTH: u32 = 412;
 pub const DISPLAY_HEIGHT: u32 = 412;
 pub const DISPLAY_X_MAX: u32 = DISPLAY_WIDTH - 1;
 pub const DISPLAY_Y_MAX: u32 = DISPLAY_HEIGHT - 1;
-pub const COLOR_BYTES: usize = 3;
-pub const BUFFER_SIZE: u32 = DISPLAY_WIDTH * DISPLAY_HEIGHT * COLOR_BYTES as u32; // 3 bytes per pixel
-pub const DMA_CHUNK_SIZE: usize = 412 * 4 * 3;
+pub const DMA_CHUNK_ROWS: usize = 4;
+// Big enough for the widest pixel format (3 bytes per pixel), used to size the DMA buffers
+pub const DMA_CHUNK_SIZE: usize = DMA_CHUNK_ROWS * DISPLAY_WIDTH as usize * 3;
 
 // The format is [ OPCODE, 0, CMD, 0, 0, PARAMS ]
 
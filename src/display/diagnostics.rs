@@ -0,0 +1,264 @@
+// Decoders for the panel's read registers, bit layouts follow MIPI DCS
+
+fn bit(value: u32, n: u32) -> bool {
+    value & (1 << n) != 0
+}
+
+/// RDDID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelId {
+    pub manufacturer: u8,
+    pub version: u8,
+    pub driver: u8,
+}
+
+impl PanelId {
+    pub fn from_bytes(bytes: [u8; 3]) -> Self {
+        Self {
+            manufacturer: bytes[0],
+            version: bytes[1],
+            driver: bytes[2],
+        }
+    }
+}
+
+/// RDDST
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayStatus {
+    pub booster_on: bool,
+    pub row_address_order: bool,
+    pub column_address_order: bool,
+    pub row_column_exchange: bool,
+    pub bgr: bool,
+    pub pixel_format: u8,
+    pub idle_mode: bool,
+    pub partial_mode: bool,
+    pub sleep_out: bool,
+    pub normal_mode: bool,
+    pub vertical_scrolling: bool,
+    pub inversion: bool,
+    pub display_on: bool,
+    pub tearing_effect_on: bool,
+    pub gamma_curve: u8,
+    pub tearing_effect_mode: bool,
+}
+
+impl DisplayStatus {
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        let v = u32::from_be_bytes(bytes);
+        Self {
+            booster_on: bit(v, 31),
+            row_address_order: bit(v, 30),
+            column_address_order: bit(v, 29),
+            row_column_exchange: bit(v, 28),
+            bgr: bit(v, 26),
+            pixel_format: ((v >> 20) & 0b111) as u8,
+            idle_mode: bit(v, 19),
+            partial_mode: bit(v, 18),
+            sleep_out: bit(v, 17),
+            normal_mode: bit(v, 16),
+            vertical_scrolling: bit(v, 15),
+            inversion: bit(v, 13),
+            display_on: bit(v, 10),
+            tearing_effect_on: bit(v, 9),
+            gamma_curve: ((v >> 6) & 0b111) as u8,
+            tearing_effect_mode: bit(v, 5),
+        }
+    }
+}
+
+/// RDDPM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerMode {
+    pub booster_on: bool,
+    pub idle_mode: bool,
+    pub partial_mode: bool,
+    pub sleep_out: bool,
+    pub normal_mode: bool,
+    pub display_on: bool,
+}
+
+impl PowerMode {
+    pub fn from_byte(byte: u8) -> Self {
+        let v = byte as u32;
+        Self {
+            booster_on: bit(v, 7),
+            idle_mode: bit(v, 6),
+            partial_mode: bit(v, 5),
+            sleep_out: bit(v, 4),
+            normal_mode: bit(v, 3),
+            display_on: bit(v, 2),
+        }
+    }
+}
+
+/// RDDSM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalMode {
+    pub tearing_effect_on: bool,
+    pub tearing_effect_mode: bool,
+    pub horizontal_sync: bool,
+    pub vertical_sync: bool,
+    pub pixel_clock: bool,
+    pub data_enable: bool,
+}
+
+impl SignalMode {
+    pub fn from_byte(byte: u8) -> Self {
+        let v = byte as u32;
+        Self {
+            tearing_effect_on: bit(v, 7),
+            tearing_effect_mode: bit(v, 6),
+            horizontal_sync: bit(v, 5),
+            vertical_sync: bit(v, 4),
+            pixel_clock: bit(v, 3),
+            data_enable: bit(v, 2),
+        }
+    }
+}
+
+/// RDDSR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfDiagnostic {
+    pub register_loading_ok: bool,
+    pub functionality_ok: bool,
+}
+
+impl SelfDiagnostic {
+    pub fn from_byte(byte: u8) -> Self {
+        let v = byte as u32;
+        Self {
+            register_loading_ok: bit(v, 7),
+            functionality_ok: bit(v, 6),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelDiagnostics {
+    pub id: PanelId,
+    pub status: DisplayStatus,
+    pub power_mode: PowerMode,
+    pub madctl: u8,
+    pub colmod: u8,
+    pub signal_mode: SignalMode,
+    pub self_diagnostic: SelfDiagnostic,
+}
+
+impl PanelDiagnostics {
+    /// A floating or shorted bus reads back as all zeroes or all ones.
+    pub fn is_responding(&self) -> bool {
+        let id = [self.id.manufacturer, self.id.version, self.id.driver];
+        id != [0x00; 3] && id != [0xFF; 3]
+    }
+
+    /// Responding, awake, displaying and happy with its own self test.
+    pub fn is_healthy(&self) -> bool {
+        self.is_responding()
+            && self.power_mode.sleep_out
+            && self.power_mode.display_on
+            && self.self_diagnostic.functionality_ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy() -> PanelDiagnostics {
+        PanelDiagnostics {
+            id: PanelId::from_bytes([0x00, 0x20, 0x10]),
+            status: DisplayStatus::from_bytes([0x9C, 0x73, 0x06, 0x60]),
+            power_mode: PowerMode::from_byte(0x9C),
+            madctl: 0,
+            colmod: 0x77,
+            signal_mode: SignalMode::from_byte(0x80),
+            self_diagnostic: SelfDiagnostic::from_byte(0xC0),
+        }
+    }
+
+    #[test]
+    fn decodes_display_status() {
+        let status = DisplayStatus::from_bytes([0x9C, 0x73, 0x06, 0x60]);
+        assert_eq!(
+            status,
+            DisplayStatus {
+                booster_on: true,
+                row_address_order: false,
+                column_address_order: false,
+                row_column_exchange: true,
+                bgr: true,
+                pixel_format: 0b111,
+                idle_mode: false,
+                partial_mode: false,
+                sleep_out: true,
+                normal_mode: true,
+                vertical_scrolling: false,
+                inversion: false,
+                display_on: true,
+                tearing_effect_on: true,
+                gamma_curve: 1,
+                tearing_effect_mode: true,
+            }
+        );
+
+        // Each flag from its own bit
+        let status = DisplayStatus::from_bytes([0x60, 0x8C, 0xA0, 0x80]);
+        assert!(!status.booster_on && status.row_address_order && status.column_address_order);
+        assert!(!status.row_column_exchange && !status.bgr);
+        assert_eq!(status.pixel_format, 0);
+        assert!(status.idle_mode && status.partial_mode && !status.sleep_out);
+        assert!(status.vertical_scrolling && status.inversion && !status.display_on);
+        assert!(!status.tearing_effect_on && !status.tearing_effect_mode);
+        assert_eq!(status.gamma_curve, 0b010);
+    }
+
+    #[test]
+    fn decodes_single_byte_registers() {
+        assert_eq!(
+            PowerMode::from_byte(0x9C),
+            PowerMode {
+                booster_on: true,
+                idle_mode: false,
+                partial_mode: false,
+                sleep_out: true,
+                normal_mode: true,
+                display_on: true,
+            }
+        );
+        let power = PowerMode::from_byte(0x60);
+        assert!(power.idle_mode && power.partial_mode && !power.sleep_out && !power.display_on);
+
+        let signal = SignalMode::from_byte(0xC4);
+        assert!(signal.tearing_effect_on && signal.tearing_effect_mode && signal.data_enable);
+        assert!(!signal.horizontal_sync && !signal.vertical_sync && !signal.pixel_clock);
+
+        let diagnostic = SelfDiagnostic::from_byte(0x80);
+        assert!(diagnostic.register_loading_ok && !diagnostic.functionality_ok);
+    }
+
+    #[test]
+    fn health_needs_an_id_an_awake_panel_and_a_passed_self_test() {
+        assert!(healthy().is_healthy());
+
+        for id in [[0x00; 3], [0xFF; 3]] {
+            let diagnostics = PanelDiagnostics {
+                id: PanelId::from_bytes(id),
+                ..healthy()
+            };
+            assert!(!diagnostics.is_responding());
+            assert!(!diagnostics.is_healthy());
+        }
+
+        // Asleep, display off, failed self test
+        for (power, diagnostic) in [(0x8C, 0xC0), (0x98, 0xC0), (0x9C, 0x80)] {
+            let diagnostics = PanelDiagnostics {
+                power_mode: PowerMode::from_byte(power),
+                self_diagnostic: SelfDiagnostic::from_byte(diagnostic),
+                ..healthy()
+            };
+            assert!(diagnostics.is_responding());
+            assert!(!diagnostics.is_healthy());
+        }
+    }
+}
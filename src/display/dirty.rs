@@ -0,0 +1,152 @@
+use embedded_graphics::{
+    prelude::{Point, Size},
+    primitives::Rectangle,
+};
+use heapless::Vec;
+
+use super::config::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+// Anything past this gets merged into the closest existing region
+pub const MAX_DIRTY_REGIONS: usize = 8;
+
+// The SPD2010 only accepts column windows that start on a multiple of 4 and
+// span a multiple of 4 pixels, so every region is widened to fit.
+const COLUMN_ALIGN: i32 = 4;
+
+/// Bounded list of framebuffer areas that changed since the last flush.
+pub struct DirtyRegions {
+    regions: Vec<Rectangle, MAX_DIRTY_REGIONS>,
+    column_align: i32,
+}
+
+impl DirtyRegions {
+    /// Regions widened to what the panel accepts as a column window.
+    pub const fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+            column_align: COLUMN_ALIGN,
+        }
+    }
+
+    /// Regions kept to the pixel, for damage in UI coordinates that gets
+    /// redrawn before it reaches the panel, e.g. compositor layers.
+    pub const fn unaligned() -> Self {
+        Self {
+            regions: Vec::new(),
+            column_align: 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rectangle> {
+        self.regions.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// Removes and returns every region, leaving the list empty.
+    pub fn take(&mut self) -> Vec<Rectangle, MAX_DIRTY_REGIONS> {
+        core::mem::take(&mut self.regions)
+    }
+
+    /// Number of pixels that a dirty flush would send.
+    pub fn pixel_count(&self) -> u32 {
+        self.regions
+            .iter()
+            .map(|r| r.size.width * r.size.height)
+            .sum()
+    }
+
+    pub fn mark_all(&mut self) {
+        self.regions.clear();
+        self.regions.push(full_screen()).ok();
+    }
+
+    pub fn add(&mut self, area: Rectangle) {
+        let Some(mut area) = align_to(area, self.column_align) else {
+            return;
+        };
+
+        loop {
+            if self.regions.iter().any(|r| contains_rect(r, &area)) {
+                return;
+            }
+
+            // Swallow anything that overlaps or touches the new area, which
+            // may make it overlap others, so go round again
+            if let Some(i) = self.regions.iter().position(|r| touches(r, &area)) {
+                area = union(&self.regions.swap_remove(i), &area);
+                continue;
+            }
+
+            if !self.regions.is_full() {
+                self.regions.push(area).ok();
+                return;
+            }
+
+            // No room left: merge with whichever region grows the least
+            let (i, _) = self
+                .regions
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (i, area_of(&union(r, &area)) - area_of(r)))
+                .min_by_key(|(_, growth)| *growth)
+                .unwrap();
+            area = union(&self.regions.swap_remove(i), &area);
+        }
+    }
+}
+
+impl Default for DirtyRegions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn full_screen() -> Rectangle {
+    Rectangle::new(Point::zero(), Size::new(DISPLAY_WIDTH, DISPLAY_HEIGHT))
+}
+
+/// Clips to the screen and widens to the panel's column alignment.
+pub fn align(area: Rectangle) -> Option<Rectangle> {
+    align_to(area, COLUMN_ALIGN)
+}
+
+fn align_to(area: Rectangle, column_align: i32) -> Option<Rectangle> {
+    let area = area.intersection(&full_screen());
+    let bottom_right = area.bottom_right()?;
+
+    let x1 = area.top_left.x - area.top_left.x % column_align;
+    let x2 = (bottom_right.x / column_align + 1) * column_align - 1;
+    let x2 = x2.min(DISPLAY_WIDTH as i32 - 1);
+
+    Some(Rectangle::with_corners(
+        Point::new(x1, area.top_left.y),
+        Point::new(x2, bottom_right.y),
+    ))
+}
+
+fn union(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    let (a_br, b_br) = (a.bottom_right().unwrap(), b.bottom_right().unwrap());
+    Rectangle::with_corners(
+        a.top_left.component_min(b.top_left),
+        a_br.component_max(b_br),
+    )
+}
+
+fn touches(a: &Rectangle, b: &Rectangle) -> bool {
+    !a.offset(1).intersection(b).is_zero_sized()
+}
+
+fn contains_rect(outer: &Rectangle, inner: &Rectangle) -> bool {
+    outer.contains(inner.top_left) && outer.contains(inner.bottom_right().unwrap())
+}
+
+fn area_of(r: &Rectangle) -> u32 {
+    r.size.width * r.size.height
+}
@@ -0,0 +1,447 @@
+// Double buffering: the app draws into one buffer while a scanout task streams
+// the other to the panel.
+//
+// `Spd2010::into_double_buffered` splits the driver in two. The drawing half
+// keeps the whole `Spd2010` API but its commands are queued for the scanout,
+// which owns the real bus. Run the scanout from its own task (ideally on the
+// second core):
+//
+//     #[embassy_executor::task]
+//     async fn scanout(scanout: Scanout<SpiDmaBus<'static, Blocking>, Input<'static>, Rgb888>) {
+//         scanout.run().await
+//     }
+
+use alloc::{boxed::Box, collections::VecDeque};
+use core::marker::PhantomData;
+
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    channel::{Channel, TrySendError},
+};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::primitives::Rectangle;
+use heapless::Vec;
+
+use super::{
+    color::ColorPipeline,
+    config::DISPLAY_WIDTH,
+    dirty::MAX_DIRTY_REGIONS,
+    draw::{Spd2010, send_frame, visible_regions},
+    error::Error,
+    pixel_format::PixelFormat,
+    power::PowerState,
+    stats::FrameTiming,
+    transport::{QspiTransport, TearingEffect},
+};
+use crate::println;
+
+// Longest command payload that can be queued (VSCRDEF takes 6)
+pub const MAX_COMMAND_LEN: usize = 8;
+const QUEUE_DEPTH: usize = 8;
+
+enum Request {
+    Command {
+        cmd: u8,
+        data: Vec<u8, MAX_COMMAND_LEN>,
+    },
+    Delay(Duration),
+    Frame {
+        pixels: Box<[u8]>,
+        regions: Vec<Rectangle, MAX_DIRTY_REGIONS>,
+    },
+    Pipeline(Option<Box<ColorPipeline>>),
+}
+
+// A buffer the scanout has finished with, and how sending it went
+struct Presented {
+    pixels: Box<[u8]>,
+    timing: Option<(Instant, FrameTiming)>,
+}
+
+/// Shared between the drawing half and the scanout, usually a `static`.
+pub struct FrameQueue {
+    requests: Channel<CriticalSectionRawMutex, Request, QUEUE_DEPTH>,
+    free: Channel<CriticalSectionRawMutex, Presented, 1>,
+}
+
+impl FrameQueue {
+    pub const fn new() -> Self {
+        Self {
+            requests: Channel::new(),
+            free: Channel::new(),
+        }
+    }
+}
+
+impl Default for FrameQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkError {
+    CommandTooLong,
+    /// Pixels only go out through `present`, `flush` and `flush_dirty` on
+    /// the drawing half always fail with this
+    PixelsNeedPresent,
+    ReadUnsupported,
+}
+
+/// Transport of the drawing half, forwards commands to the scanout.
+///
+/// Commands are sent from non-async code, so the ones that don't fit in the
+/// queue wait here until the next `present`, `send_commands` or delay.
+/// There's no pixel path: the drawing half's `flush` and `flush_dirty` fail
+/// with `LinkError::PixelsNeedPresent`, use `present` instead.
+pub struct ScanoutLink {
+    queue: &'static FrameQueue,
+    // Back buffer returned by the scanout that hasn't been drawn into yet
+    spare: Option<Box<[u8]>>,
+    // Commands waiting for room in the queue, in order
+    pending: VecDeque<Request>,
+}
+
+impl ScanoutLink {
+    async fn send_pending(&mut self) {
+        while let Some(request) = self.pending.pop_front() {
+            self.queue.requests.send(request).await;
+        }
+    }
+}
+
+// Only commands, `write_pixels` always fails (see `ScanoutLink`)
+impl QspiTransport for ScanoutLink {
+    type Error = LinkError;
+
+    fn write_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let data = Vec::from_slice(data).map_err(|_| LinkError::CommandTooLong)?;
+        let request = Request::Command { cmd, data };
+        // Nothing may overtake what's already waiting
+        if !self.pending.is_empty() {
+            self.pending.push_back(request);
+        } else if let Err(TrySendError::Full(request)) = self.queue.requests.try_send(request) {
+            self.pending.push_back(request);
+        }
+        Ok(())
+    }
+
+    fn write_pixels(&mut self, _cmd: u8, _pixels: &[u8]) -> Result<(), Self::Error> {
+        Err(LinkError::PixelsNeedPresent)
+    }
+
+    fn read_command(&mut self, _cmd: u8, _buffer: &mut [u8]) -> Result<(), Self::Error> {
+        Err(LinkError::ReadUnsupported)
+    }
+
+    /// Queued, so the scanout waits between the commands on the real bus.
+    async fn delay(&mut self, duration: Duration) {
+        self.send_pending().await;
+        self.queue.requests.send(Request::Delay(duration)).await;
+    }
+}
+
+/// Owns the bus and streams presented frames to the panel.
+pub struct Scanout<B, T, C>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    qspi: B,
+    tear_input: T,
+    queue: &'static FrameQueue,
+    color_pipeline: Option<Box<ColorPipeline>>,
+    format: PhantomData<C>,
+}
+
+impl<B, T, C> Scanout<B, T, C>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    pub async fn run(mut self) -> ! {
+        loop {
+            match self.queue.requests.receive().await {
+                Request::Command { cmd, data } => {
+                    if self.qspi.write_command(cmd, &data).is_err() {
+                        println!("Scanout: command {:#04x} failed", cmd);
+                    }
+                }
+                Request::Delay(duration) => Timer::after(duration).await,
+                Request::Pipeline(color_pipeline) => self.color_pipeline = color_pipeline,
+                Request::Frame { pixels, regions } => {
+                    let timing = send_frame::<B, T, C>(
+                        &mut self.qspi,
+                        &mut self.tear_input,
+                        &pixels,
+                        &regions,
+                        self.color_pipeline.as_deref(),
+                    )
+                    .await;
+                    if timing.is_err() {
+                        println!("Scanout: frame transfer failed");
+                    }
+                    let timing = timing.ok();
+                    self.queue.free.send(Presented { pixels, timing }).await;
+                }
+            }
+        }
+    }
+}
+
+impl<B, T, C> Spd2010<B, T, C>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    /// Splits into a drawing half and a [`Scanout`], allocating the second
+    /// framebuffer.
+    pub fn into_double_buffered(
+        self,
+        queue: &'static FrameQueue,
+    ) -> (Spd2010<ScanoutLink, (), C>, Scanout<B, T, C>) {
+        let link = ScanoutLink {
+            queue,
+            spare: Some(self.framebuffer.clone()),
+            pending: VecDeque::new(),
+        };
+        let color_pipeline = self.color_pipeline().cloned().map(Box::new);
+        let (display, qspi, tear_input) = self.replace_transport(link, ());
+
+        let scanout = Scanout {
+            qspi,
+            tear_input,
+            queue,
+            color_pipeline,
+            format: PhantomData,
+        };
+        (display, scanout)
+    }
+}
+
+impl<C> Spd2010<ScanoutLink, (), C>
+where
+    C: PixelFormat,
+{
+    /// Queues everything drawn since the last call for scanout and swaps
+    /// buffers. Waits only if the previous frame is still being sent.
+    ///
+    /// Commands still waiting for the queue go out even if nothing was
+    /// drawn. Cancelling this loses nothing: the frame either hasn't been
+    /// taken yet or waits in the link for the next `present`.
+    pub async fn present(&mut self) -> Result<(), Error<LinkError>> {
+        if self.power_state() == PowerState::Sleep {
+            return Err(Error::Asleep);
+        }
+
+        self.send_commands().await;
+        if self.dirty_regions().is_empty() {
+            return Ok(());
+        }
+
+        self.wait_presented().await;
+        let regions = self.take_dirty();
+        let mut back = self.transport_mut().spare.take().unwrap();
+
+        // The back buffer is a frame behind, catch it up on what changed
+        let stride = DISPLAY_WIDTH as usize * C::BYTES;
+        for area in regions.iter() {
+            for y in area.rows() {
+                let start = y as usize * stride + area.top_left.x as usize * C::BYTES;
+                let end = start + area.size.width as usize * C::BYTES;
+                back[start..end].copy_from_slice(&self.framebuffer[start..end]);
+            }
+        }
+
+        let pixels = core::mem::replace(&mut self.framebuffer, back);
+        let regions =
+            visible_regions(&regions, self.circular_clip(), self.partial_area()).collect();
+        // Queued behind the commands, then sent like them
+        if self.take_pipeline_changed() {
+            let pipeline = self.color_pipeline().cloned().map(Box::new);
+            let request = Request::Pipeline(pipeline);
+            self.transport_mut().pending.push_back(request);
+        }
+        let request = Request::Frame { pixels, regions };
+        self.transport_mut().pending.push_back(request);
+        self.send_commands().await;
+
+        Ok(())
+    }
+
+    /// Waits until the scanout has sent the last presented frame, which
+    /// then shows in `stats`.
+    pub async fn wait_presented(&mut self) {
+        let link = self.transport_mut();
+        if link.spare.is_some() {
+            return;
+        }
+        // The frame may still be waiting for room in the queue
+        link.send_pending().await;
+        let Presented { pixels, timing } = link.queue.free.receive().await;
+        link.spare = Some(pixels);
+        if let Some((started, timing)) = timing {
+            self.record_frame(started, timing);
+        }
+    }
+
+    /// Queues the commands still waiting for room, without presenting.
+    pub async fn send_commands(&mut self) {
+        self.transport_mut().send_pending().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    use embassy_futures::{block_on, select::select};
+    use embedded_graphics::{
+        pixelcolor::Rgb888,
+        prelude::{DrawTarget, Point, RgbColor, Size},
+    };
+
+    use super::*;
+    use crate::display::{
+        lcd_command,
+        mock::{self, NoTearing, RecordingBus},
+        power::SLEEP_TOGGLE_DELAY,
+    };
+
+    // The scanout keeps its bus, so the test looks at it through a clone
+    #[derive(Clone, Default)]
+    struct SharedBus {
+        bus: Rc<RefCell<RecordingBus>>,
+    }
+
+    impl QspiTransport for SharedBus {
+        type Error = core::convert::Infallible;
+
+        fn write_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), Self::Error> {
+            self.bus.borrow_mut().write_command(cmd, data)
+        }
+
+        fn write_pixels(&mut self, cmd: u8, pixels: &[u8]) -> Result<(), Self::Error> {
+            self.bus.borrow_mut().write_pixels(cmd, pixels)
+        }
+
+        fn read_command(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+            self.bus.borrow_mut().read_command(cmd, buffer)
+        }
+    }
+
+    type Split = (
+        Spd2010<ScanoutLink, (), Rgb888>,
+        Scanout<SharedBus, NoTearing, Rgb888>,
+        SharedBus,
+    );
+
+    fn split() -> Split {
+        let bus = SharedBus::default();
+        let mut display = mock::display_on::<_, Rgb888>(bus.clone());
+        block_on(display.init()).unwrap();
+        bus.bus.borrow_mut().clear();
+
+        let queue = Box::leak(Box::new(FrameQueue::new()));
+        let (display, scanout) = display.into_double_buffered(queue);
+        (display, scanout, bus)
+    }
+
+    #[test]
+    fn command_bursts_queue_in_order() {
+        let (mut display, scanout, bus) = split();
+        display.set_stats_enabled(true);
+
+        // More than the queue holds, nothing is listening yet
+        for level in 0..3 * QUEUE_DEPTH as u8 {
+            display.set_display_brightness(level).unwrap();
+        }
+        let area = Rectangle::new(Point::new(100, 100), Size::new(8, 2));
+        let Ok(()) = display.fill_solid(&area, Rgb888::RED);
+
+        block_on(select(scanout.run(), async {
+            display.present().await.unwrap();
+            display.wait_presented().await;
+        }));
+
+        let bus = bus.bus.borrow();
+        let levels: alloc::vec::Vec<u8> = bus
+            .commands()
+            .filter(|t| t.cmd == lcd_command::WRDISBV)
+            .map(|t| t.data[0])
+            .collect();
+        assert!(levels.iter().copied().eq(0..3 * QUEUE_DEPTH as u8));
+        assert_eq!(bus.windows(), [area]);
+
+        // The frame was sent by the scanout but counts for the display
+        let stats = display.stats().unwrap();
+        assert_eq!(stats.frames(), 1);
+        assert_eq!(stats.last().bytes, 8 * 2 * 3);
+        assert_eq!(stats.last().chunks, 2);
+    }
+
+    #[test]
+    fn present_sends_commands_without_drawing() {
+        let (mut display, scanout, bus) = split();
+        for level in 0..2 * QUEUE_DEPTH as u8 {
+            display.set_display_brightness(level).unwrap();
+        }
+        assert!(!display.transport().pending.is_empty());
+
+        block_on(select(scanout.run(), async {
+            display.present().await.unwrap();
+            assert!(display.transport().pending.is_empty());
+            while bus.bus.borrow().transfers.len() < 2 * QUEUE_DEPTH {
+                Timer::after(Duration::from_millis(1)).await;
+            }
+        }));
+        assert_eq!(bus.bus.borrow().pixel_transfers(), 0);
+    }
+
+    #[test]
+    fn cancelled_present_keeps_the_damage() {
+        let (mut display, scanout, bus) = split();
+        let first = Rectangle::new(Point::new(0, 0), Size::new(8, 1));
+        let second = Rectangle::new(Point::new(0, 8), Size::new(8, 1));
+
+        // Nothing scans out yet, so the first frame stays queued
+        let Ok(()) = display.fill_solid(&first, Rgb888::RED);
+        block_on(display.present()).unwrap();
+        // Given up on while waiting for the first frame to come back
+        let Ok(()) = display.fill_solid(&second, Rgb888::BLUE);
+        block_on(select(display.present(), async {}));
+        assert!(!display.dirty_regions().is_empty());
+
+        block_on(select(scanout.run(), async {
+            display.present().await.unwrap();
+            display.wait_presented().await;
+        }));
+        assert_eq!(bus.bus.borrow().windows(), [first, second]);
+    }
+
+    #[test]
+    fn power_delays_run_on_the_scanout() {
+        let (mut display, scanout, bus) = split();
+
+        block_on(select(scanout.run(), async {
+            let started = Instant::now();
+            display.set_power_state(PowerState::Sleep).await.unwrap();
+            // Only queued, the drawing side doesn't wait for the panel
+            assert!(started.elapsed() < SLEEP_TOGGLE_DELAY / 2);
+
+            while bus.bus.borrow().transfers.len() < 2 {
+                Timer::after(Duration::from_millis(5)).await;
+            }
+        }));
+
+        let sent: alloc::vec::Vec<u8> = bus.bus.borrow().commands().map(|t| t.cmd).collect();
+        assert_eq!(sent, [lcd_command::DISPOFF, lcd_command::SLPIN]);
+        // Init woke the panel just before, so SLPIN had to wait
+        let times = &bus.bus.borrow().times;
+        assert!(times[1] - times[0] >= SLEEP_TOGGLE_DELAY / 2);
+    }
+}
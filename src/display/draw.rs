@@ -1,103 +1,165 @@
-use alloc::boxed::Box;
-use embassy_time::{Duration, Timer};
+use alloc::{boxed::Box, vec::Vec};
+use core::marker::PhantomData;
+use embassy_time::{Duration, Instant};
 use embedded_graphics::{
     Pixel,
     pixelcolor::Rgb888,
-    prelude::{Dimensions, DrawTarget, Point, RgbColor, Size},
-    primitives::Rectangle,
-};
-use esp_hal::{
-    DriverMode,
-    gpio::{Input, InputConfig, InputPin, Pull},
-    spi::{
-        self,
-        master::{Address, Command, DataMode, SpiDmaBus},
-    },
+    prelude::{Dimensions, DrawTarget, Point, Size},
+    primitives::{PointsIter, Rectangle},
 };
 
-use crate::display::COLOR_BYTES;
-
 use super::{
-    config::{
-        BUFFER_SIZE, DISPLAY_HEIGHT, DISPLAY_WIDTH, DISPLAY_X_MAX, DISPLAY_Y_MAX, DMA_CHUNK_SIZE,
+    color::{ColorPipeline, GammaCurve},
+    config::{DISPLAY_HEIGHT, DISPLAY_WIDTH, DISPLAY_X_MAX, DISPLAY_Y_MAX},
+    diagnostics::{
+        DisplayStatus, PanelDiagnostics, PanelId, PowerMode, SelfDiagnostic, SignalMode,
     },
-    init_cmd::LCD_INIT_CMD,
-    lcd_command, opcode,
+    dirty::{self, DirtyRegions, MAX_DIRTY_REGIONS},
+    error::Error,
+    init_sequence::InitSequence,
+    lcd_command,
+    orientation::{Orientation, Transform},
+    pixel_format::{PixelFormat, blend},
+    power::{PowerState, SLEEP_IN_DELAY, SLEEP_TOGGLE_DELAY},
+    round,
+    scroll::{PartialArea, Scroll, ScrollArea},
+    stats::{FrameStats, FrameTiming},
+    tearing::{FramePacer, TearingMode},
+    text::{self, Font, HorizontalAlignment, TextStyle, VerticalAlignment},
+    transport::{QspiTransport, TearingEffect},
 };
 
-pub struct Spd2010<'a, Dm>
+pub struct Spd2010<B, T, C = Rgb888>
 where
-    Dm: DriverMode,
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
 {
-    qspi: SpiDmaBus<'a, Dm>,
+    qspi: B,
     pub framebuffer: Box<[u8]>,
-    tear_input: Input<'a>,
+    tear_input: T,
+    dirty: DirtyRegions,
+    orientation: Orientation,
+    // What's left of `orientation` after MADCTL, applied in `draw_iter`
+    transform: Transform,
+    madctl: u8,
+    // Drop pixels outside the visible circle
+    circular_clip: bool,
+    scroll: Option<Scroll>,
+    partial: Option<PartialArea>,
+    init_sequence: InitSequence,
+    // Applied on the way to the panel, None when it wouldn't change anything
+    color_pipeline: Option<ColorPipeline>,
+    // Set when the pipeline changes, so a scanout can pick it up
+    pipeline_changed: bool,
+    stats: Option<FrameStats>,
+    power_state: PowerState,
+    // Last SLPIN / SLPOUT, the panel needs time between the two
+    sleep_changed_at: Instant,
+    format: PhantomData<C>,
 }
 
-impl<'a, Dm> Spd2010<'a, Dm>
+impl<B, T, C> Spd2010<B, T, C>
 where
-    Dm: DriverMode,
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
 {
-    pub fn new(qspi: SpiDmaBus<'a, Dm>, tear_pin: impl InputPin + 'a) -> Self {
-        let framebuffer =
-            unsafe { Box::<[u8]>::new_zeroed_slice(BUFFER_SIZE as usize).assume_init() };
-
-        let config = InputConfig::default().with_pull(Pull::Up);
-        let tear_input = Input::new(tear_pin, config);
+    pub fn new(qspi: B, tear_input: T) -> Self {
+        let framebuffer = unsafe { Box::<[u8]>::new_zeroed_slice(C::BUFFER_SIZE).assume_init() };
 
         Self {
             qspi,
             framebuffer,
             tear_input,
+            dirty: DirtyRegions::new(),
+            orientation: Orientation::default(),
+            transform: Transform::default(),
+            madctl: 0,
+            circular_clip: false,
+            scroll: None,
+            partial: None,
+            init_sequence: InitSequence::default(),
+            color_pipeline: None,
+            pipeline_changed: false,
+            stats: None,
+            // Coming out of reset the panel is asleep
+            power_state: PowerState::Sleep,
+            sleep_changed_at: Instant::now(),
+            format: PhantomData,
         }
     }
 
-    fn set_draw_pos(&mut self, x1: u16, y1: u16, x2: u16, y2: u16) -> Result<(), spi::Error> {
-        // [ x1 (byte 2), x1 (byte 1), x2 (byte 2), x2 (byte 1) ]
-        // 2 before 1 because Endian and stuff
-        let x_set_data: [u8; 4] = [
-            (x1 >> 8) as u8,
-            (x1 & 0xFF) as u8,
-            (x2 >> 8) as u8,
-            (x2 & 0xFF) as u8,
-        ];
-        // dbg!(&x_set_data);
-        self.send_command(lcd_command::CASET, &x_set_data)?;
-
-        let y_set_data: [u8; 4] = [
-            (y1 >> 8) as u8,
-            (y1 & 0xFF) as u8,
-            (y2 >> 8) as u8,
-            (y2 & 0xFF) as u8,
-        ];
-        // dbg!(&y_set_data);
-        self.send_command(lcd_command::RASET, &y_set_data)?;
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Rotates / mirrors everything drawn from now on. The framebuffer keeps
+    /// its old contents, so redraw after calling this.
+    pub fn set_orientation(&mut self, orientation: Orientation) -> Result<(), B::Error> {
+        let (madctl, transform) = orientation.split();
+        self.send_command(lcd_command::MADCTL, &[madctl])?;
+
+        self.orientation = orientation;
+        self.transform = transform;
+        self.madctl = madctl;
+        self.dirty.mark_all();
 
         Ok(())
     }
 
-    fn send_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), spi::Error> {
-        let address_value = (cmd as u32) << 8;
-        self.qspi.half_duplex_write(
-            DataMode::Single,
-            Command::_8Bit(opcode::WRITE_CMD as u16, DataMode::Single),
-            Address::_24Bit(address_value, DataMode::Single),
-            0,
-            data,
-        )?;
-        Ok(())
+    pub fn circular_clip(&self) -> bool {
+        self.circular_clip
     }
 
-    fn send_pixels(qspi: &mut SpiDmaBus<'a, Dm>, cmd: u8, pixels: &[u8]) -> Result<(), spi::Error> {
-        let address_value = (cmd as u32) << 8;
-        qspi.half_duplex_write(
-            DataMode::Quad,
-            Command::_8Bit(opcode::WRITE_COLOR as u16, DataMode::Single),
-            Address::_24Bit(address_value, DataMode::Single),
-            0,
-            pixels,
-        )?;
-        Ok(())
+    /// Stops drawing into the invisible corners, which also keeps them out
+    /// of dirty flushes.
+    pub fn set_circular_clip(&mut self, enabled: bool) {
+        self.circular_clip = enabled;
+    }
+
+    fn send_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), B::Error> {
+        self.qspi.write_command(cmd, data)
+    }
+
+    pub fn read_register(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), B::Error> {
+        self.qspi.read_command(cmd, buffer)
+    }
+
+    fn read_byte(&mut self, cmd: u8) -> Result<u8, B::Error> {
+        let mut buffer = [0u8; 1];
+        self.read_register(cmd, &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    pub fn read_diagnostics(&mut self) -> Result<PanelDiagnostics, B::Error> {
+        let mut id = [0u8; 3];
+        self.read_register(lcd_command::RDDID, &mut id)?;
+        let mut status = [0u8; 4];
+        self.read_register(lcd_command::RDDST, &mut status)?;
+
+        Ok(PanelDiagnostics {
+            id: PanelId::from_bytes(id),
+            status: DisplayStatus::from_bytes(status),
+            power_mode: PowerMode::from_byte(self.read_byte(lcd_command::RDDPM)?),
+            madctl: self.read_byte(lcd_command::RDD_MADCTL)?,
+            colmod: self.read_byte(lcd_command::RDD_COLMOD)?,
+            signal_mode: SignalMode::from_byte(self.read_byte(lcd_command::RDDSM)?),
+            self_diagnostic: SelfDiagnostic::from_byte(self.read_byte(lcd_command::RDDSR)?),
+        })
+    }
+
+    /// Gives back the transport and tearing effect input, e.g. to inspect a mock.
+    pub fn release(self) -> (B, T) {
+        (self.qspi, self.tear_input)
+    }
+
+    pub fn transport(&self) -> &B {
+        &self.qspi
+    }
+
+    pub fn transport_mut(&mut self) -> &mut B {
+        &mut self.qspi
     }
 
     // pub fn draw_rect(&mut self, x1: u16, y1: u16, x2: u16, y2: u16, r: u8, g: u8, b: u8) {
@@ -111,77 +173,1236 @@ where
     //     }
     // }
 
-    pub async fn flush(&mut self) -> Result<(), spi::Error> {
-        self.set_draw_pos(
-            0,
-            0,
-            (DISPLAY_WIDTH as u16) - 1,
-            (DISPLAY_HEIGHT as u16) - 1,
-        )?;
-
-        self.tear_input.wait_for_falling_edge().await;
-        let mut is_first = true;
-        for chunk in self.framebuffer.chunks(DMA_CHUNK_SIZE) {
-            if is_first {
-                Self::send_pixels(&mut self.qspi, lcd_command::RAMWR, chunk)?;
-                is_first = false;
-            } else {
-                Self::send_pixels(&mut self.qspi, lcd_command::RAMWRC, chunk)?;
-            }
+    pub async fn flush(&mut self) -> Result<(), Error<B::Error>> {
+        if self.power_state == PowerState::Sleep {
+            return Err(Error::Asleep);
         }
 
+        let regions: heapless::Vec<Rectangle, 1> =
+            visible_regions(&[dirty::full_screen()], false, self.partial).collect();
+        self.send_regions(&regions).await?;
+        self.dirty.clear();
+
+        Ok(())
+    }
+
+    /// Only sends the areas drawn to since the last flush.
+    pub async fn flush_dirty(&mut self) -> Result<(), Error<B::Error>> {
+        if self.power_state == PowerState::Sleep {
+            return Err(Error::Asleep);
+        }
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+
+        // Only forgotten once sent, so a failed flush can be retried
+        let regions: heapless::Vec<Rectangle, MAX_DIRTY_REGIONS> =
+            self.dirty.iter().copied().collect();
+        let regions: heapless::Vec<Rectangle, MAX_DIRTY_REGIONS> =
+            visible_regions(&regions, self.circular_clip, self.partial).collect();
+        self.send_regions(&regions).await?;
+        self.dirty.clear();
+
+        Ok(())
+    }
+
+    async fn send_regions(&mut self, regions: &[Rectangle]) -> Result<(), B::Error> {
+        let (started, timing) = send_frame::<B, T, C>(
+            &mut self.qspi,
+            &mut self.tear_input,
+            &self.framebuffer,
+            regions,
+            self.color_pipeline.as_ref(),
+        )
+        .await?;
+        self.record_frame(started, timing);
+
         Ok(())
     }
 
-    pub async fn init(&mut self) -> Result<(), spi::Error> {
-        for (cmd, delay, data) in LCD_INIT_CMD {
-            self.send_command(*cmd, &data)?;
-            Timer::after(Duration::from_millis(*delay as u64)).await;
+    // Also for frames the scanout sent on this display's behalf
+    pub(crate) fn record_frame(&mut self, started: Instant, timing: FrameTiming) {
+        if let Some(stats) = &mut self.stats {
+            stats.record(started, timing);
+        }
+    }
+
+    pub fn stats(&self) -> Option<&FrameStats> {
+        self.stats.as_ref()
+    }
+
+    /// Starts (or stops and drops) collecting `FrameStats` on every flush.
+    pub fn set_stats_enabled(&mut self, enabled: bool) {
+        match (enabled, self.stats.is_some()) {
+            (true, false) => self.stats = Some(FrameStats::new()),
+            (false, _) => self.stats = None,
+            _ => {}
+        }
+    }
+
+    /// Draws the stats summary into `area`, e.g. along the bottom of the
+    /// safe rectangle. Drawing it dirties `area`, which shows in the stats.
+    pub fn draw_stats_overlay(&mut self, area: &Rectangle, color: C) {
+        let Some(stats) = &self.stats else {
+            return;
+        };
+        let summary = stats.summary();
+        let style = TextStyle::new(Font::Small, color)
+            .with_background(C::BLACK)
+            .with_alignment(HorizontalAlignment::Center, VerticalAlignment::Center);
+        // The summary is ASCII, every glyph is there
+        text::draw_text(self, &summary, area, &style).ok();
+    }
+
+    pub(crate) fn take_dirty(&mut self) -> heapless::Vec<Rectangle, MAX_DIRTY_REGIONS> {
+        self.dirty.take()
+    }
+
+    /// Moves the drawing state over to a different transport.
+    pub(crate) fn replace_transport<B2, T2>(
+        self,
+        qspi: B2,
+        tear_input: T2,
+    ) -> (Spd2010<B2, T2, C>, B, T)
+    where
+        B2: QspiTransport,
+        T2: TearingEffect,
+    {
+        let Self {
+            qspi: old_qspi,
+            framebuffer,
+            tear_input: old_tear_input,
+            dirty,
+            orientation,
+            transform,
+            madctl,
+            circular_clip,
+            scroll,
+            partial,
+            init_sequence,
+            color_pipeline,
+            pipeline_changed,
+            stats,
+            power_state,
+            sleep_changed_at,
+            format,
+        } = self;
+
+        let display = Spd2010 {
+            qspi,
+            framebuffer,
+            tear_input,
+            dirty,
+            orientation,
+            transform,
+            madctl,
+            circular_clip,
+            scroll,
+            partial,
+            init_sequence,
+            color_pipeline,
+            pipeline_changed,
+            stats,
+            power_state,
+            sleep_changed_at,
+            format,
+        };
+        (display, old_qspi, old_tear_input)
+    }
+
+    /// Marks an area as changed, for when `framebuffer` is written directly.
+    pub fn mark_dirty(&mut self, area: Rectangle) {
+        self.dirty.add(area);
+    }
+
+    pub fn dirty_regions(&self) -> &DirtyRegions {
+        &self.dirty
+    }
+
+    /// Replaces the init sequence `init` sends, e.g. for another panel revision.
+    pub fn with_init_sequence(mut self, init_sequence: InitSequence) -> Self {
+        self.init_sequence = init_sequence;
+        self
+    }
+
+    pub fn init_sequence(&self) -> &InitSequence {
+        &self.init_sequence
+    }
+
+    pub fn color_pipeline(&self) -> Option<&ColorPipeline> {
+        self.color_pipeline.as_ref()
+    }
+
+    /// Transforms colours from the next flush on, the framebuffer itself is
+    /// left alone. Everything is resent.
+    pub fn set_color_pipeline(&mut self, pipeline: ColorPipeline) {
+        self.color_pipeline = (!pipeline.is_identity()).then_some(pipeline);
+        self.pipeline_changed = true;
+        self.dirty.mark_all();
+    }
+
+    pub(crate) fn take_pipeline_changed(&mut self) -> bool {
+        core::mem::take(&mut self.pipeline_changed)
+    }
+
+    /// Selects one of the panel's own gamma curves (GAMSET).
+    pub fn set_gamma_curve(&mut self, curve: GammaCurve) -> Result<(), B::Error> {
+        self.send_command(lcd_command::GAMSET, &[curve as u8])
+    }
+
+    pub async fn init(&mut self) -> Result<(), B::Error> {
+        for command in self.init_sequence.iter() {
+            self.qspi.write_command(command.cmd, command.data)?;
+            self.qspi
+                .delay(Duration::from_millis(command.delay as u64))
+                .await;
         }
 
+        self.send_command(lcd_command::COLMOD, &[C::COLMOD])?;
+        self.send_command(lcd_command::MADCTL, &[self.madctl])?;
         self.send_command(lcd_command::DISPON, &[])?;
 
+        // The init sequence ends with SLPOUT
+        self.power_state = PowerState::Active;
+        self.sleep_changed_at = Instant::now();
+
+        Ok(())
+    }
+
+    /// Turns the TE output on in the given mode, or off with `None`.
+    pub fn set_tearing_effect(&mut self, mode: Option<TearingMode>) -> Result<(), B::Error> {
+        match mode {
+            Some(mode) => self.send_command(lcd_command::TEON, &[mode as u8]),
+            None => self.send_command(lcd_command::TEOFF, &[]),
+        }
+    }
+
+    /// Makes TE fire when the panel reaches `line` instead of at the end of the frame.
+    pub fn set_tear_scanline(&mut self, line: u16) -> Result<(), B::Error> {
+        self.send_command(lcd_command::STE, &line.to_be_bytes())
+    }
+
+    /// Line the panel is currently scanning out (GDCAN).
+    pub fn read_scanline(&mut self) -> Result<u16, B::Error> {
+        let mut buffer = [0u8; 2];
+        self.read_register(lcd_command::GDCAN, &mut buffer)?;
+        Ok(u16::from_be_bytes(buffer))
+    }
+
+    /// Waits for the next frame slot of `pacer`, on this display's TE input.
+    /// Returns how many slots were missed. A double buffered drawing half has
+    /// no TE input, so there it's paced by the timer alone.
+    pub async fn wait_frame(&mut self, pacer: &mut FramePacer) -> u32 {
+        pacer.wait(&mut self.tear_input).await
+    }
+
+    /// Times `frames` TE pulses to find how long one refresh takes.
+    pub async fn measure_refresh_period(&mut self, frames: u32) -> Duration {
+        let frames = frames.max(1);
+        self.tear_input.wait_for_tear().await;
+        let start = Instant::now();
+        for _ in 0..frames {
+            self.tear_input.wait_for_tear().await;
+        }
+        start.elapsed() / frames
+    }
+
+    pub fn partial_area(&self) -> Option<PartialArea> {
+        self.partial
+    }
+
+    /// Only lights up `area`, the rest of the panel shows black. Ends scrolling.
+    pub fn set_partial_area(&mut self, area: PartialArea) -> Result<(), B::Error> {
+        self.send_command(lcd_command::PTLAR, &area.ptlar_data())?;
+        self.send_command(lcd_command::PTLON, &[])?;
+        self.partial = Some(area);
+        self.scroll = None;
         Ok(())
     }
 
+    /// Leaves partial and scrolling modes. Rows outside the partial area
+    /// weren't kept up to date, so everything is resent on the next flush.
+    pub fn set_normal_mode(&mut self) -> Result<(), B::Error> {
+        self.send_command(lcd_command::NORON, &[])?;
+        if self.partial.take().is_some() {
+            self.dirty.mark_all();
+        }
+        self.scroll = None;
+        Ok(())
+    }
+
+    pub fn scroll(&self) -> Option<Scroll> {
+        self.scroll
+    }
+
+    /// Starts hardware scrolling of `area` at offset 0, leaving partial mode
+    /// first. Rows in the old and new scroll areas now show different parts
+    /// of the framebuffer, so they're marked dirty; redraw them.
+    pub fn define_scroll_area(&mut self, area: ScrollArea) -> Result<(), B::Error> {
+        if self.partial.is_some() {
+            self.set_normal_mode()?;
+        }
+        self.send_command(lcd_command::VSCRDEF, &area.vscrdef_data())?;
+        if let Some(old) = self.scroll {
+            self.dirty.add(old.area.rows());
+        }
+        self.dirty.add(area.rows());
+        self.scroll = Some(Scroll { area, offset: 0 });
+        self.set_scroll_offset(0)
+    }
+
+    /// Moves the scrolling rows up by `offset`. Drawing keeps using screen
+    /// coordinates, rows are remapped to wherever they now sit in memory.
+    pub fn set_scroll_offset(&mut self, offset: u16) -> Result<(), B::Error> {
+        let Some(mut scroll) = self.scroll else {
+            return Ok(());
+        };
+        scroll.offset = offset % scroll.area.height.max(1);
+        self.send_command(lcd_command::VSCSAD, &scroll.start_address().to_be_bytes())?;
+        self.scroll = Some(scroll);
+        Ok(())
+    }
+
+    // Frame memory row behind panel row `row`
+    fn memory_row(&self, row: i32) -> i32 {
+        match self.scroll {
+            Some(scroll) => scroll.memory_row(row as u16) as i32,
+            None => row,
+        }
+    }
+
+    // Dirty an area given in panel rows, which may be scattered in memory
+    fn mark_rows_dirty(&mut self, area: Rectangle) {
+        let Some(scroll) = self.scroll else {
+            self.dirty.add(area);
+            return;
+        };
+        for y in area.rows() {
+            let row = Point::new(area.top_left.x, scroll.memory_row(y as u16) as i32);
+            self.dirty
+                .add(Rectangle::new(row, Size::new(area.size.width, 1)));
+        }
+    }
+
+    /// Writes the panel's own brightness register (WRDISBV).
+    pub fn set_display_brightness(&mut self, level: u8) -> Result<(), B::Error> {
+        self.send_command(lcd_command::WRDISBV, &[level])
+    }
+
+    pub fn power_state(&self) -> PowerState {
+        self.power_state
+    }
+
+    pub async fn set_power_state(&mut self, state: PowerState) -> Result<(), B::Error> {
+        if state == self.power_state {
+            return Ok(());
+        }
+
+        if self.power_state == PowerState::Sleep {
+            self.wait_for_sleep_toggle().await;
+            self.send_command(lcd_command::SLPOUT, &[])?;
+            self.sleep_changed_at = Instant::now();
+            self.qspi.delay(SLEEP_TOGGLE_DELAY).await;
+        }
+
+        match state {
+            PowerState::Active => {
+                self.send_command(lcd_command::IDMOFF, &[])?;
+                self.send_command(lcd_command::DISPON, &[])?;
+            }
+            PowerState::Idle => {
+                self.send_command(lcd_command::IDMON, &[])?;
+                self.send_command(lcd_command::DISPON, &[])?;
+            }
+            PowerState::DisplayOff => {
+                self.send_command(lcd_command::DISPOFF, &[])?;
+            }
+            PowerState::Sleep => {
+                self.send_command(lcd_command::DISPOFF, &[])?;
+                self.wait_for_sleep_toggle().await;
+                self.send_command(lcd_command::SLPIN, &[])?;
+                self.sleep_changed_at = Instant::now();
+                self.qspi.delay(SLEEP_IN_DELAY).await;
+            }
+        }
+
+        self.power_state = state;
+
+        Ok(())
+    }
+
+    // The delays go through the transport so a queued one (the scanout of a
+    // double buffered display) keeps them between the right commands
+    async fn wait_for_sleep_toggle(&mut self) {
+        let ready = self.sleep_changed_at + SLEEP_TOGGLE_DELAY;
+        let remaining = ready.saturating_duration_since(Instant::now());
+        self.qspi.delay(remaining).await;
+    }
+
+    /// Framebuffer colour at `point`, in the same coordinates as drawing.
+    pub fn pixel(&self, point: Point) -> Option<C> {
+        let (x @ 0..=DISPLAY_X_MAX, y @ 0..=DISPLAY_Y_MAX) = point.try_into().ok()? else {
+            return None;
+        };
+        let (x, y) = self.transform.map(x, y);
+        let y = self.memory_row(y as i32) as u32;
+        let pixel_index = (((y * DISPLAY_WIDTH) + x) as usize) * C::BYTES;
+        let bytes = &self.framebuffer[pixel_index..pixel_index + C::BYTES];
+        Some(C::from_bytes(bytes))
+    }
+
     pub fn fill(&mut self) {
-        for i in 0..(self.framebuffer.len() - 1) {
-            self.framebuffer[i] = 0x00;
+        self.framebuffer.fill(0x00);
+        self.dirty.add(dirty::full_screen());
+    }
+
+    // Framebuffer bytes for pixels x1..=x2 of row y, in framebuffer coordinates
+    fn row_bytes(&mut self, y: i32, x1: i32, x2: i32) -> &mut [u8] {
+        let row = y as usize * DISPLAY_WIDTH as usize;
+        let start = (row + x1 as usize) * C::BYTES;
+        let end = (row + x2 as usize + 1) * C::BYTES;
+        &mut self.framebuffer[start..end]
+    }
+
+    // Visible part of x1..=x2 on row y
+    fn clip_span(&self, y: i32, x1: i32, x2: i32) -> Option<(i32, i32)> {
+        if !self.circular_clip {
+            return Some((x1, x2));
+        }
+        let (span_x1, span_x2) = round::row_span(y)?;
+        let (x1, x2) = (x1.max(span_x1), x2.min(span_x2));
+        (x1 <= x2).then_some((x1, x2))
+    }
+
+    // Blends `color` into pixels x1..=x2 of row y by `alpha(x)`, in the same
+    // coordinates as drawing. Lets anti-aliasing write straight into the
+    // framebuffer without collecting the pixels first.
+    pub(crate) fn blend_row(
+        &mut self,
+        y: i32,
+        x1: i32,
+        x2: i32,
+        color: C,
+        mut alpha: impl FnMut(i32) -> u8,
+    ) {
+        if !(0..=DISPLAY_Y_MAX as i32).contains(&y) {
+            return;
+        }
+        let (x1, x2) = (x1.max(0), x2.min(DISPLAY_X_MAX as i32));
+        // The circle is the same in drawing and framebuffer coordinates
+        let Some((x1, x2)) = self.clip_span(y, x1, x2) else {
+            return;
+        };
+
+        let foreground = color.into();
+        let mut min = Point::new(i32::MAX, i32::MAX);
+        let mut max = Point::new(i32::MIN, i32::MIN);
+
+        for x in x1..=x2 {
+            let alpha = alpha(x);
+            if alpha == 0 {
+                continue;
+            }
+            let (fb_x, fb_y) = self.transform.map(x as u32, y as u32);
+            let fb = Point::new(fb_x as i32, self.memory_row(fb_y as i32));
+            let bytes = self.row_bytes(fb.y, fb.x, fb.x);
+            let blended = match alpha {
+                0xFF => color,
+                _ => blend(C::from_bytes(bytes).into(), foreground, alpha).into(),
+            };
+            blended.write_bytes(bytes);
+            min = min.component_min(fb);
+            max = max.component_max(fb);
+        }
+
+        if min.x <= max.x {
+            self.dirty.add(Rectangle::with_corners(min, max));
+        }
+    }
+}
+
+pub(crate) fn set_draw_pos<B: QspiTransport>(
+    qspi: &mut B,
+    x1: u16,
+    y1: u16,
+    x2: u16,
+    y2: u16,
+) -> Result<(), B::Error> {
+    // [ x1 (byte 2), x1 (byte 1), x2 (byte 2), x2 (byte 1) ]
+    // 2 before 1 because Endian and stuff
+    let x_set_data: [u8; 4] = [
+        (x1 >> 8) as u8,
+        (x1 & 0xFF) as u8,
+        (x2 >> 8) as u8,
+        (x2 & 0xFF) as u8,
+    ];
+    // dbg!(&x_set_data);
+    qspi.write_command(lcd_command::CASET, &x_set_data)?;
+
+    let y_set_data: [u8; 4] = [
+        (y1 >> 8) as u8,
+        (y1 & 0xFF) as u8,
+        (y2 >> 8) as u8,
+        (y2 & 0xFF) as u8,
+    ];
+    // dbg!(&y_set_data);
+    qspi.write_command(lcd_command::RASET, &y_set_data)?;
+
+    Ok(())
+}
+
+// What of `regions` the panel shows: nothing outside the partial area, and
+// nothing in the corners if they're clipped
+pub(crate) fn visible_regions(
+    regions: &[Rectangle],
+    circular_clip: bool,
+    partial: Option<PartialArea>,
+) -> impl Iterator<Item = Rectangle> + '_ {
+    regions.iter().filter_map(move |area| {
+        let area = match partial {
+            Some(partial) => partial.clip(area)?,
+            None => *area,
+        };
+        if circular_clip {
+            round::clip_rect(&area).and_then(dirty::align)
+        } else {
+            Some(area)
         }
+    })
+}
+
+// Waits for TE and sends the areas, timing it all. Returns when it started
+// along with the timing.
+pub(crate) async fn send_frame<B, T, C>(
+    qspi: &mut B,
+    tear_input: &mut T,
+    framebuffer: &[u8],
+    regions: &[Rectangle],
+    pipeline: Option<&ColorPipeline>,
+) -> Result<(Instant, FrameTiming), B::Error>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    let started = Instant::now();
+    tear_input.wait_for_tear().await;
+    let tear_done = Instant::now();
+
+    let mut timing = FrameTiming::default();
+    for area in regions {
+        timing.chunks += send_window::<B, C>(qspi, framebuffer, area, pipeline)?;
+        timing.bytes += (area.size.width * area.size.height) as usize * C::BYTES;
     }
+
+    let done = Instant::now();
+    timing.tear_wait = tear_done - started;
+    timing.transfer = done - tear_done;
+    timing.total = done - started;
+
+    Ok((started, timing))
 }
 
-impl<'a, Dm> DrawTarget for Spd2010<'a, Dm>
+pub(crate) fn send_window<B: QspiTransport, C: PixelFormat>(
+    qspi: &mut B,
+    framebuffer: &[u8],
+    area: &Rectangle,
+    pipeline: Option<&ColorPipeline>,
+) -> Result<u32, B::Error> {
+    let Some(bottom_right) = area.bottom_right() else {
+        return Ok(0);
+    };
+    let (x1, y1) = (area.top_left.x as usize, area.top_left.y as usize);
+    let (x2, y2) = (bottom_right.x as usize, bottom_right.y as usize);
+
+    set_draw_pos(qspi, x1 as u16, y1 as u16, x2 as u16, y2 as u16)?;
+
+    let stride = DISPLAY_WIDTH as usize * C::BYTES;
+    let row_start = |y: usize| y * stride + x1 * C::BYTES;
+    let row_end = |y: usize| y * stride + (x2 + 1) * C::BYTES;
+
+    // Transformed pixels go out from a copy
+    let mut scratch = Vec::new();
+    let mut write = |cmd: u8, pixels: &[u8]| match pipeline {
+        Some(pipeline) => {
+            scratch.clear();
+            scratch.extend_from_slice(pixels);
+            pipeline.apply::<C>(&mut scratch);
+            qspi.write_pixels(cmd, &scratch)
+        }
+        None => qspi.write_pixels(cmd, pixels),
+    };
+
+    let mut cmd = lcd_command::RAMWR;
+    let mut chunks = 0;
+    if area.size.width == DISPLAY_WIDTH {
+        // Full rows are contiguous in the framebuffer
+        let window = &framebuffer[row_start(y1)..row_end(y2)];
+        for chunk in window.chunks(C::DMA_CHUNK_SIZE) {
+            write(cmd, chunk)?;
+            cmd = lcd_command::RAMWRC;
+            chunks += 1;
+        }
+    } else {
+        for y in y1..=y2 {
+            let row = &framebuffer[row_start(y)..row_end(y)];
+            write(cmd, row)?;
+            cmd = lcd_command::RAMWRC;
+            chunks += 1;
+        }
+    }
+
+    Ok(chunks)
+}
+
+fn fill_pattern(bytes: &mut [u8], pixel: &[u8]) {
+    if pixel.iter().all(|b| *b == pixel[0]) {
+        bytes.fill(pixel[0]);
+    } else {
+        for chunk in bytes.chunks_exact_mut(pixel.len()) {
+            chunk.copy_from_slice(pixel);
+        }
+    }
+}
+
+impl<B, T, C> DrawTarget for Spd2010<B, T, C>
 where
-    Dm: DriverMode,
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
 {
-    type Color = Rgb888;
+    type Color = C;
     type Error = core::convert::Infallible;
 
     fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
     where
         I: IntoIterator<Item = embedded_graphics::Pixel<Self::Color>>,
     {
+        let mut min = Point::new(i32::MAX, i32::MAX);
+        let mut max = Point::new(i32::MIN, i32::MIN);
+
         for Pixel(coord, color) in pixels.into_iter() {
             if let Ok((x @ 0..=DISPLAY_X_MAX, y @ 0..=DISPLAY_Y_MAX)) = coord.try_into() {
+                if self.circular_clip && !round::is_visible(coord) {
+                    continue;
+                }
+                let (x, y) = self.transform.map(x, y);
+                let y = self.memory_row(y as i32) as u32;
                 // Calculate the index in the framebuffer.
-                let pixel_index: u32 = ((y * (DISPLAY_WIDTH)) + x) * COLOR_BYTES as u32;
+                let pixel_index = (((y * (DISPLAY_WIDTH)) + x) as usize) * C::BYTES;
                 // println!("{x}, {y} -> {pixel_index}");
-                self.framebuffer[pixel_index as usize] = color.r();
-                self.framebuffer[pixel_index as usize + 1] = color.g();
-                self.framebuffer[pixel_index as usize + 2] = color.b();
+                color.write_bytes(&mut self.framebuffer[pixel_index..pixel_index + C::BYTES]);
+
+                let coord = Point::new(x as i32, y as i32);
+                min = min.component_min(coord);
+                max = max.component_max(coord);
+            }
+        }
+
+        if min.x <= max.x {
+            self.dirty.add(Rectangle::with_corners(min, max));
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        // The circle and the screen look the same under every orientation, so
+        // the fill can be done entirely in framebuffer space
+        let Some(fb_area) = self.transform.map_rect(&area) else {
+            return Ok(());
+        };
+        let (x1, x2) = (
+            fb_area.top_left.x,
+            fb_area.top_left.x + fb_area.size.width as i32 - 1,
+        );
+
+        let mut pixel = [0u8; 3];
+        color.write_bytes(&mut pixel);
+        let pixel = &pixel[..C::BYTES];
+
+        for y in fb_area.rows() {
+            if let Some((x1, x2)) = self.clip_span(y, x1, x2) {
+                let row = self.memory_row(y);
+                fill_pattern(self.row_bytes(row, x1, x2), pixel);
+            }
+        }
+
+        self.mark_rows_dirty(fb_area);
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        // Columns become rows, no contiguous runs left to copy
+        if self.transform.swap {
+            let pixels = area.points().zip(colors).map(|(p, c)| Pixel(p, c));
+            return self.draw_iter(pixels);
+        }
+
+        let drawn = area.intersection(&self.bounding_box());
+        let Some(drawn_bottom_right) = drawn.bottom_right() else {
+            return Ok(());
+        };
+
+        let width = area.size.width as usize;
+        let mut colors = colors.into_iter();
+
+        for y in area.rows() {
+            let mut row_colors = colors.by_ref().take(width);
+
+            let span = (drawn.top_left.y..=drawn_bottom_right.y)
+                .contains(&y)
+                .then(|| self.clip_span(y, drawn.top_left.x, drawn_bottom_right.x))
+                .flatten();
+
+            if let Some((x1, x2)) = span {
+                row_colors
+                    .by_ref()
+                    .take((x1 - area.top_left.x) as usize)
+                    .for_each(drop);
+
+                // MADCTL does any x flip, so columns stay where they are
+                let fb_y = self.memory_row(self.transform.map(0, y as u32).1 as i32);
+                let bytes = self.row_bytes(fb_y, x1, x2);
+                let run = row_colors.by_ref().take((x2 - x1 + 1) as usize);
+                bytes
+                    .chunks_exact_mut(C::BYTES)
+                    .zip(run)
+                    .for_each(|(p, c)| c.write_bytes(p));
             }
+
+            // Whatever is left of the row is off screen
+            row_colors.for_each(drop);
+        }
+
+        if let Some(fb_area) = self.transform.map_rect(&drawn) {
+            self.mark_rows_dirty(fb_area);
         }
 
         Ok(())
     }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let mut pixel = [0u8; 3];
+        color.write_bytes(&mut pixel);
+        fill_pattern(&mut self.framebuffer, &pixel[..C::BYTES]);
+        self.dirty.mark_all();
+
+        Ok(())
+    }
 }
 
-impl<'a, Dm> Dimensions for Spd2010<'a, Dm>
+impl<B, T, C> Dimensions for Spd2010<B, T, C>
 where
-    Dm: DriverMode,
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
 {
     fn bounding_box(&self) -> embedded_graphics::primitives::Rectangle {
-        Rectangle::new(Point::zero(), Size::new(DISPLAY_WIDTH, DISPLAY_HEIGHT))
+        // Square panel, but keep the swap honest
+        let size = if self.transform.swap {
+            Size::new(DISPLAY_HEIGHT, DISPLAY_WIDTH)
+        } else {
+            Size::new(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+        };
+        Rectangle::new(Point::zero(), size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+    use embedded_graphics::{
+        pixelcolor::{Rgb565, Rgb888},
+        prelude::RgbColor,
+    };
+
+    use alloc::format;
+
+    use super::*;
+    use crate::display::{
+        mock::{self, NoTearing, RecordingBus},
+        orientation::{Rotation, madctl},
+    };
+
+    type Display<C = Rgb888> = Spd2010<RecordingBus, NoTearing, C>;
+
+    // Fails every transfer while `broken` is set
+    #[derive(Default)]
+    struct FlakyBus {
+        bus: RecordingBus,
+        broken: bool,
+    }
+
+    impl QspiTransport for FlakyBus {
+        type Error = ();
+
+        fn write_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), ()> {
+            if self.broken {
+                return Err(());
+            }
+            let Ok(()) = self.bus.write_command(cmd, data);
+            Ok(())
+        }
+
+        fn write_pixels(&mut self, cmd: u8, pixels: &[u8]) -> Result<(), ()> {
+            if self.broken {
+                return Err(());
+            }
+            let Ok(()) = self.bus.write_pixels(cmd, pixels);
+            Ok(())
+        }
+
+        fn read_command(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), ()> {
+            if self.broken {
+                return Err(());
+            }
+            let Ok(()) = self.bus.read_command(cmd, buffer);
+            Ok(())
+        }
+    }
+
+    fn awake<B: QspiTransport, C: PixelFormat>(bus: B) -> Spd2010<B, NoTearing, C> {
+        let mut display = mock::display_on(bus);
+        assert!(block_on(display.init()).is_ok());
+        display
+    }
+
+    // Awake, with the bus log cleared
+    fn display<C: PixelFormat>() -> Display<C> {
+        let mut display = awake(RecordingBus::new());
+        display.transport_mut().clear();
+        display
+    }
+
+    #[test]
+    fn init_sends_sequence_then_format() {
+        let mut display = mock::display::<Rgb565>().with_init_sequence(InitSequence::default());
+        block_on(display.init()).unwrap();
+
+        let expected: Vec<u8> = display
+            .init_sequence()
+            .iter()
+            .map(|command| command.cmd)
+            .chain([
+                lcd_command::COLMOD,
+                lcd_command::MADCTL,
+                lcd_command::DISPON,
+            ])
+            .collect();
+        let sent: Vec<u8> = display.transport().commands().map(|t| t.cmd).collect();
+        assert_eq!(sent, expected);
+
+        let colmod = display
+            .transport()
+            .commands()
+            .filter(|t| t.cmd == lcd_command::COLMOD)
+            .last()
+            .unwrap();
+        // The vendor table writes 0x3A on another page too, ours comes last
+        assert_eq!(colmod.data, [Rgb565::COLMOD]);
+        assert_eq!(display.power_state(), PowerState::Active);
+    }
+
+    #[test]
+    fn flush_needs_the_panel_awake() {
+        let mut display = mock::display::<Rgb888>();
+        assert_eq!(block_on(display.flush()), Err(Error::Asleep));
+        assert_eq!(block_on(display.flush_dirty()), Err(Error::Asleep));
+        assert!(display.transport().transfers.is_empty());
+    }
+
+    // Commands sent going to `state`
+    fn transition(display: &mut Display, state: PowerState) -> Vec<u8> {
+        display.transport_mut().clear();
+        block_on(display.set_power_state(state)).unwrap();
+        assert_eq!(display.power_state(), state);
+        display.transport().commands().map(|t| t.cmd).collect()
+    }
+
+    #[test]
+    fn power_transitions_send_commands_in_order() {
+        use PowerState::*;
+        use lcd_command::*;
+
+        let mut display = display::<Rgb888>();
+        assert_eq!(transition(&mut display, Active), []);
+        assert_eq!(transition(&mut display, Idle), [IDMON, DISPON]);
+        assert_eq!(transition(&mut display, DisplayOff), [DISPOFF]);
+        assert_eq!(transition(&mut display, Active), [IDMOFF, DISPON]);
+        assert_eq!(transition(&mut display, Sleep), [DISPOFF, SLPIN]);
+        assert_eq!(transition(&mut display, Idle), [SLPOUT, IDMON, DISPON]);
+        assert_eq!(transition(&mut display, Sleep), [DISPOFF, SLPIN]);
+        assert_eq!(transition(&mut display, DisplayOff), [SLPOUT, DISPOFF]);
+    }
+
+    #[test]
+    fn sleep_commands_keep_their_distance() {
+        let mut display = display::<Rgb888>();
+        // Init ended with SLPOUT just now
+        let woken = Instant::now();
+        block_on(display.set_power_state(PowerState::Sleep)).unwrap();
+        let asleep = Instant::now();
+        block_on(display.set_power_state(PowerState::Active)).unwrap();
+
+        let bus = display.transport();
+        let at = |cmd| {
+            let index = bus.transfers.iter().position(|t| t.cmd == cmd).unwrap();
+            bus.times[index]
+        };
+        let (slpin, slpout) = (at(lcd_command::SLPIN), at(lcd_command::SLPOUT));
+        assert!(slpin - woken >= SLEEP_TOGGLE_DELAY);
+        assert!(asleep - slpin >= SLEEP_IN_DELAY);
+        assert!(slpout - slpin >= SLEEP_TOGGLE_DELAY);
+        assert!(at(lcd_command::IDMOFF) - slpout >= SLEEP_TOGGLE_DELAY);
+    }
+
+    #[test]
+    fn flush_sends_the_whole_framebuffer() {
+        let mut display = display::<Rgb565>();
+        let Ok(()) = display.clear(Rgb565::RED);
+        block_on(display.flush()).unwrap();
+
+        let (bus, tearing) = display.release();
+        assert_eq!(tearing.waits, 1);
+        assert_eq!(bus.windows(), [dirty::full_screen()]);
+        assert_eq!(bus.pixel_bytes(), Rgb565::BUFFER_SIZE);
+        assert_eq!(
+            bus.pixel_transfers(),
+            Rgb565::BUFFER_SIZE.div_ceil(Rgb565::DMA_CHUNK_SIZE)
+        );
+
+        // The first chunk starts the write, the rest continue it
+        let mut pixels = bus.transfers.iter().filter(|t| t.is_pixels());
+        assert_eq!(pixels.next().unwrap().cmd, lcd_command::RAMWR);
+        assert!(pixels.all(|t| t.cmd == lcd_command::RAMWRC));
+        assert!(
+            bus.transfers
+                .iter()
+                .filter(|t| t.is_pixels())
+                .flat_map(|t| t.data.chunks_exact(2))
+                .all(|pixel| pixel == [0xF8, 0x00])
+        );
+    }
+
+    #[test]
+    fn orientation_sets_madctl() {
+        let mut display = display::<Rgb888>();
+        display
+            .set_orientation(Orientation::new(Rotation::Deg180))
+            .unwrap();
+
+        let sent: Vec<_> = display.transport().commands().cloned().collect();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].cmd, lcd_command::MADCTL);
+        assert_eq!(sent[0].data, [madctl::MX]);
+        // Everything has to be redrawn
+        assert!(!display.dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn tearing_commands() {
+        let mut display = display::<Rgb888>();
+        display
+            .set_tearing_effect(Some(TearingMode::VBlank))
+            .unwrap();
+        display
+            .set_tearing_effect(Some(TearingMode::VAndHBlank))
+            .unwrap();
+        display.set_tearing_effect(None).unwrap();
+        display.set_tear_scanline(0x0123).unwrap();
+
+        let sent: Vec<_> = display
+            .transport()
+            .commands()
+            .map(|t| (t.cmd, t.data.clone()))
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                (lcd_command::TEON, vec![0x00]),
+                (lcd_command::TEON, vec![0x01]),
+                (lcd_command::TEOFF, vec![]),
+                (lcd_command::STE, vec![0x01, 0x23]),
+            ]
+        );
+
+        display
+            .transport_mut()
+            .set_register(lcd_command::GDCAN, &[0x01, 0x9B]);
+        assert_eq!(display.read_scanline(), Ok(411));
+        let read = display.transport().transfers.last().unwrap();
+        assert!(read.is_read() && read.cmd == lcd_command::GDCAN);
+    }
+
+    fn is_dirty(display: &Display<Rgb565>, area: Rectangle) -> bool {
+        let corners = [area.top_left, area.bottom_right().unwrap()];
+        display
+            .dirty_regions()
+            .iter()
+            .any(|region| corners.iter().all(|&corner| region.contains(corner)))
+    }
+
+    #[test]
+    fn scroll_areas_dirty_their_rows() {
+        let mut display = display::<Rgb565>();
+        let first = ScrollArea::new(100, 100);
+        display.define_scroll_area(first).unwrap();
+        assert!(is_dirty(&display, first.rows()));
+        assert!(!is_dirty(&display, dirty::full_screen()));
+
+        // Moving the rows is what hardware scrolling is for, nothing to send
+        block_on(display.flush_dirty()).unwrap();
+        display.set_scroll_offset(40).unwrap();
+        assert!(display.dirty_regions().is_empty());
+
+        let second = ScrollArea::new(20, 300);
+        display.define_scroll_area(second).unwrap();
+        assert!(is_dirty(&display, first.rows()));
+        assert!(is_dirty(&display, second.rows()));
+        assert_eq!(display.scroll().unwrap().offset, 0);
+    }
+
+    #[test]
+    fn scrolling_leaves_partial_mode() {
+        let mut display = display::<Rgb565>();
+        display
+            .set_partial_area(PartialArea::new(100, 199).unwrap())
+            .unwrap();
+        display.transport_mut().clear();
+
+        let area = ScrollArea::new(0, 0);
+        display.define_scroll_area(area).unwrap();
+        let sent: Vec<u8> = display.transport().commands().map(|t| t.cmd).collect();
+        assert_eq!(
+            sent,
+            [
+                lcd_command::NORON,
+                lcd_command::VSCRDEF,
+                lcd_command::VSCSAD
+            ]
+        );
+        assert_eq!(display.partial_area(), None);
+        assert_eq!(display.scroll().unwrap().area, area);
+
+        // The rows that were dark come back
+        display.transport_mut().clear();
+        block_on(display.flush_dirty()).unwrap();
+        assert_eq!(display.transport().windows(), [dirty::full_screen()]);
+    }
+
+    #[test]
+    fn partial_mode_only_sends_lit_rows() {
+        let mut display = display::<Rgb565>();
+        let area = PartialArea::new(100, 199).unwrap();
+        display.set_partial_area(area).unwrap();
+        let lit = Rectangle::new(Point::new(0, 100), Size::new(DISPLAY_WIDTH, 100));
+        let row_bytes = DISPLAY_WIDTH as usize * Rgb565::BYTES;
+
+        display.transport_mut().clear();
+        block_on(display.flush()).unwrap();
+        assert_eq!(display.transport().windows(), [lit]);
+        assert_eq!(display.transport().pixel_bytes(), 100 * row_bytes);
+
+        // One region straddling the top edge, one entirely outside
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 90), Size::new(DISPLAY_WIDTH, 20)),
+                Rgb565::RED,
+            )
+            .unwrap();
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(0, 300), Size::new(DISPLAY_WIDTH, 20)),
+                Rgb565::RED,
+            )
+            .unwrap();
+        display.transport_mut().clear();
+        block_on(display.flush_dirty()).unwrap();
+        assert_eq!(
+            display.transport().windows(),
+            [Rectangle::new(
+                Point::new(0, 100),
+                Size::new(DISPLAY_WIDTH, 10)
+            )]
+        );
+        assert_eq!(display.transport().pixel_bytes(), 10 * row_bytes);
+        assert!(display.dirty_regions().is_empty());
+
+        // The unlit rows missed updates
+        display.set_normal_mode().unwrap();
+        display.transport_mut().clear();
+        block_on(display.flush_dirty()).unwrap();
+        assert_eq!(display.transport().windows(), [dirty::full_screen()]);
+    }
+
+    #[test]
+    fn reads_diagnostics_from_registers() {
+        let mut display = display::<Rgb888>();
+        let bus = display.transport_mut();
+        bus.set_register(lcd_command::RDD_MADCTL, &[madctl::MX]);
+        bus.set_register(lcd_command::RDD_COLMOD, &[Rgb888::COLMOD]);
+
+        let diagnostics = display.read_diagnostics().unwrap();
+        assert_eq!(diagnostics.madctl, madctl::MX);
+        assert_eq!(diagnostics.colmod, Rgb888::COLMOD);
+        assert!(display.transport().transfers.iter().all(|t| t.is_read()));
+    }
+
+    #[test]
+    fn flush_dirty_sends_only_what_changed() {
+        let mut display = display::<Rgb888>();
+        let area = Rectangle::new(Point::new(10, 20), Size::new(6, 4));
+        let Ok(()) = display.fill_solid(&area, Rgb888::GREEN);
+        block_on(display.flush_dirty()).unwrap();
+
+        // Widened to whole groups of four columns
+        let window = Rectangle::new(Point::new(8, 20), Size::new(8, 4));
+        let bus = display.transport();
+        assert_eq!(bus.windows(), [window]);
+        assert_eq!(bus.pixel_bytes(), 8 * 4 * 3);
+        assert_eq!(bus.pixel_transfers(), 4);
+        assert!(display.dirty_regions().is_empty());
+
+        // Nothing new, nothing sent
+        display.transport_mut().clear();
+        block_on(display.flush_dirty()).unwrap();
+        assert!(display.transport().transfers.is_empty());
+        assert_eq!(display.release().1.waits, 1);
+    }
+
+    #[test]
+    fn flush_dirty_sends_each_region() {
+        let mut display = display::<Rgb565>();
+        let areas = [
+            Rectangle::new(Point::new(100, 50), Size::new(16, 2)),
+            Rectangle::new(Point::new(300, 300), Size::new(4, 10)),
+        ];
+        for area in &areas {
+            let Ok(()) = display.fill_solid(area, Rgb565::BLUE);
+        }
+        block_on(display.flush_dirty()).unwrap();
+
+        let bus = display.transport();
+        assert_eq!(bus.windows(), areas);
+        assert_eq!(bus.pixel_bytes(), (16 * 2 + 4 * 10) * 2);
+        assert_eq!(bus.pixel_transfers(), 2 + 10);
+    }
+
+    #[test]
+    fn flush_dirty_sends_full_rows_in_chunks() {
+        let mut display = display::<Rgb888>();
+        let rows = Rectangle::new(Point::new(0, 100), Size::new(DISPLAY_WIDTH, 40));
+        let Ok(()) = display.fill_solid(&rows, Rgb888::WHITE);
+        block_on(display.flush_dirty()).unwrap();
+
+        let bytes = DISPLAY_WIDTH as usize * 40 * 3;
+        let bus = display.transport();
+        assert_eq!(bus.windows(), [rows]);
+        assert_eq!(bus.pixel_bytes(), bytes);
+        assert_eq!(
+            bus.pixel_transfers(),
+            bytes.div_ceil(Rgb888::DMA_CHUNK_SIZE)
+        );
+    }
+
+    #[test]
+    fn flush_dirty_skips_the_corners() {
+        let mut display = display::<Rgb888>();
+        display.set_circular_clip(true);
+        display.mark_dirty(Rectangle::new(Point::zero(), Size::new(16, 16)));
+        block_on(display.flush_dirty()).unwrap();
+
+        assert_eq!(display.transport().pixel_bytes(), 0);
+        assert!(display.dirty_regions().is_empty());
+    }
+
+    #[test]
+    fn failed_flush_keeps_dirty_regions() {
+        let mut display: Spd2010<FlakyBus, NoTearing, Rgb888> = awake(FlakyBus::default());
+        let area = Rectangle::new(Point::new(40, 40), Size::new(8, 8));
+        let Ok(()) = display.fill_solid(&area, Rgb888::RED);
+
+        display.transport_mut().broken = true;
+        assert_eq!(block_on(display.flush_dirty()), Err(Error::Bus(())));
+        assert_eq!(display.dirty_regions().iter().collect::<Vec<_>>(), [&area]);
+
+        let bus = display.transport_mut();
+        bus.broken = false;
+        bus.bus.clear();
+        block_on(display.flush_dirty()).unwrap();
+        assert_eq!(display.transport().bus.windows(), [area]);
+        assert!(display.dirty_regions().is_empty());
+    }
+
+    // Same drawing through `draw_iter`, which every fast path has to match
+    fn per_pixel(display: &mut Display, area: &Rectangle, colors: impl Fn(Point) -> Rgb888) {
+        let pixels = area.points().map(|p| Pixel(p, colors(p)));
+        let Ok(()) = display.draw_iter(pixels);
+    }
+
+    #[test]
+    fn fills_match_per_pixel_drawing() {
+        let areas = [
+            Rectangle::new(Point::new(50, 70), Size::new(33, 17)),
+            // Off the top left, through the clipped corner
+            Rectangle::new(Point::new(-5, -3), Size::new(60, 80)),
+            // Off the bottom right
+            Rectangle::new(Point::new(380, 300), Size::new(40, 120)),
+            Rectangle::new(Point::new(0, 200), Size::new(DISPLAY_WIDTH, 12)),
+        ];
+        let gradient = |p: Point| Rgb888::new(p.x as u8, p.y as u8, (p.x ^ p.y) as u8);
+        let rotations = [
+            Rotation::Deg0,
+            Rotation::Deg90,
+            Rotation::Deg180,
+            Rotation::Deg270,
+        ];
+
+        for rotation in rotations {
+            for (mirror_x, mirror_y) in [(false, false), (true, false), (false, true), (true, true)]
+            {
+                for (clip, scroll) in [(false, false), (true, false), (false, true), (true, true)] {
+                    let orientation = Orientation::new(rotation)
+                        .with_mirror_x(mirror_x)
+                        .with_mirror_y(mirror_y);
+                    let setup = || {
+                        let mut display = display::<Rgb888>();
+                        display.set_orientation(orientation).unwrap();
+                        display.set_circular_clip(clip);
+                        if scroll {
+                            display.define_scroll_area(ScrollArea::new(20, 40)).unwrap();
+                            display.set_scroll_offset(123).unwrap();
+                        }
+                        display
+                    };
+                    let case = format!("{orientation:?}, clip {clip}, scroll {scroll}");
+
+                    for area in &areas {
+                        let (mut fast, mut slow) = (setup(), setup());
+                        let Ok(()) = fast.fill_solid(area, Rgb888::CYAN);
+                        per_pixel(&mut slow, area, |_| Rgb888::CYAN);
+                        assert!(
+                            fast.framebuffer == slow.framebuffer,
+                            "fill_solid {area:?}, {case}"
+                        );
+
+                        let (mut fast, mut slow) = (setup(), setup());
+                        let colors = area.points().map(gradient);
+                        let Ok(()) = fast.fill_contiguous(area, colors);
+                        per_pixel(&mut slow, area, gradient);
+                        assert!(
+                            fast.framebuffer == slow.framebuffer,
+                            "fill_contiguous {area:?}, {case}"
+                        );
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,15 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The transport failed
+    Bus(E),
+    /// Pixels can't be written while the panel is in sleep mode
+    Asleep,
+    /// A buffer passed in can't hold the data asked for
+    BufferTooSmall,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Self {
+        Error::Bus(error)
+    }
+}
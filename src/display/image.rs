@@ -0,0 +1,830 @@
+// Bitmap assets (BMP, TGA, QOI) decoded row by row straight into the
+// framebuffer. Images are kept encoded, so a full screen splash only costs
+// its file size plus one row of RGBA while drawing.
+
+use alloc::{borrow::Cow, vec, vec::Vec};
+
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::{Dimensions, DrawTarget, OriginDimensions, Point, Size},
+    primitives::Rectangle,
+};
+use embedded_io::Read;
+
+use super::{
+    draw::Spd2010,
+    pixel_format::{PixelFormat, blend},
+    transport::{QspiTransport, TearingEffect},
+};
+
+// Decoded pixels are RGBA
+type Rgba = [u8; 4];
+
+// Anything bigger is almost certainly a corrupt header
+const MAX_DIMENSION: u32 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Bmp,
+    Tga,
+    Qoi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError {
+    /// Not BMP, TGA or QOI
+    UnknownFormat,
+    /// A variant of the format that isn't decoded, e.g. compressed BMPs
+    Unsupported,
+    /// The data ends before the image does
+    Truncated,
+    /// The reader failed
+    Read,
+}
+
+/// How `Spd2010::draw_image` treats transparency.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlitOptions {
+    /// Blend with what's already in the framebuffer, otherwise alpha is ignored
+    pub alpha: bool,
+    /// Pixels of this colour are left out
+    pub color_key: Option<Rgb888>,
+}
+
+impl BlitOptions {
+    pub fn with_alpha(mut self) -> Self {
+        self.alpha = true;
+        self
+    }
+
+    pub fn with_color_key(mut self, color_key: Rgb888) -> Self {
+        self.color_key = Some(color_key);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BmpHeader {
+    pixels: usize,
+    stride: usize,
+    bpp: u16,
+    top_down: bool,
+    // R, G, B, A for 16 and 32 bit images
+    masks: [u32; 4],
+    palette: usize,
+    palette_len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TgaHeader {
+    pixels: usize,
+    bpp: u8,
+    rle: bool,
+    gray: bool,
+    top_down: bool,
+    right_to_left: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Header {
+    Bmp(BmpHeader),
+    Tga(TgaHeader),
+    Qoi,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image<'a> {
+    data: Cow<'a, [u8]>,
+    header: Header,
+    size: Size,
+    has_alpha: bool,
+}
+
+impl<'a> Image<'a> {
+    /// Parses the header, e.g. of an `include_bytes!` asset. Errors in the
+    /// pixel data only show up when drawing.
+    pub fn new(data: impl Into<Cow<'a, [u8]>>) -> Result<Self, ImageError> {
+        let data = data.into();
+        let (header, size, has_alpha) = match &data[..] {
+            [b'B', b'M', ..] => parse_bmp(&data)?,
+            [b'q', b'o', b'i', b'f', ..] => parse_qoi(&data)?,
+            // TGA has no magic, it's whatever is left
+            _ => parse_tga(&data)?,
+        };
+
+        if size.width > MAX_DIMENSION || size.height > MAX_DIMENSION {
+            return Err(ImageError::Unsupported);
+        }
+
+        Ok(Self {
+            data,
+            header,
+            size,
+            has_alpha,
+        })
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        match self.header {
+            Header::Bmp(_) => ImageFormat::Bmp,
+            Header::Tga(_) => ImageFormat::Tga,
+            Header::Qoi => ImageFormat::Qoi,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.has_alpha
+    }
+
+    // Calls `row` with every decoded row, in whatever order the file has them
+    fn decode_rows(&self, row: impl FnMut(u32, &[Rgba])) -> Result<(), ImageError> {
+        match self.header {
+            Header::Bmp(header) => self.decode_bmp(header, row),
+            Header::Tga(header) => self.decode_tga(header, row),
+            Header::Qoi => self.decode_qoi(row),
+        }
+    }
+
+    fn decode_bmp(
+        &self,
+        header: BmpHeader,
+        mut row: impl FnMut(u32, &[Rgba]),
+    ) -> Result<(), ImageError> {
+        let (width, height) = (self.size.width as usize, self.size.height);
+        let mut pixels = vec![[0u8; 4]; width];
+
+        for r in 0..height {
+            let start = header.pixels + r as usize * header.stride;
+            let src = bytes(&self.data, start, header.stride)?;
+
+            for (x, pixel) in pixels.iter_mut().enumerate() {
+                *pixel = match header.bpp {
+                    24 => [src[x * 3 + 2], src[x * 3 + 1], src[x * 3], 0xFF],
+                    16 => {
+                        let value = u16::from_le_bytes([src[x * 2], src[x * 2 + 1]]);
+                        from_masks(value as u32, &header.masks)
+                    }
+                    32 => {
+                        let value = le32(src, x * 4)?;
+                        from_masks(value, &header.masks)
+                    }
+                    // Palette indices, packed from the top bit down
+                    bpp => {
+                        let bpp = bpp as usize;
+                        let bit = x * bpp;
+                        let index = (src[bit / 8] >> (8 - bpp - bit % 8)) & ((1 << bpp) - 1) as u8;
+                        let index = (index as usize).min(header.palette_len.saturating_sub(1));
+                        match bytes(&self.data, header.palette.saturating_add(index * 4), 4) {
+                            Ok([b, g, r, _]) => [*r, *g, *b, 0xFF],
+                            _ => [0, 0, 0, 0xFF],
+                        }
+                    }
+                };
+            }
+
+            let y = if header.top_down { r } else { height - 1 - r };
+            row(y, &pixels);
+        }
+
+        Ok(())
+    }
+
+    fn decode_tga(
+        &self,
+        header: TgaHeader,
+        mut row: impl FnMut(u32, &[Rgba]),
+    ) -> Result<(), ImageError> {
+        let (width, height) = (self.size.width as usize, self.size.height);
+        let bytes_per_pixel = (header.bpp as usize).div_ceil(8);
+        let mut pixels = vec![[0u8; 4]; width];
+
+        let mut offset = header.pixels;
+        // RLE packets can run across rows
+        let mut packet_left = 0;
+        let mut repeat = false;
+        let mut pixel = [0u8; 4];
+
+        for r in 0..height {
+            for out in pixels.iter_mut() {
+                if header.rle && packet_left == 0 {
+                    let packet = bytes(&self.data, offset, 1)?[0];
+                    offset += 1;
+                    packet_left = (packet & 0x7F) as usize + 1;
+                    repeat = packet & 0x80 != 0;
+                    if repeat {
+                        pixel =
+                            self.tga_pixel(&header, bytes(&self.data, offset, bytes_per_pixel)?);
+                        offset += bytes_per_pixel;
+                    }
+                }
+                if !repeat {
+                    pixel = self.tga_pixel(&header, bytes(&self.data, offset, bytes_per_pixel)?);
+                    offset += bytes_per_pixel;
+                }
+                packet_left = packet_left.saturating_sub(1);
+                *out = pixel;
+            }
+
+            if header.right_to_left {
+                pixels.reverse();
+            }
+            let y = if header.top_down { r } else { height - 1 - r };
+            row(y, &pixels);
+        }
+
+        Ok(())
+    }
+
+    fn tga_pixel(&self, header: &TgaHeader, src: &[u8]) -> Rgba {
+        let alpha = |a: u8| if self.has_alpha { a } else { 0xFF };
+        match (header.gray, src) {
+            (true, [v]) => [*v, *v, *v, 0xFF],
+            (true, [v, a, ..]) => [*v, *v, *v, alpha(*a)],
+            (false, [lo, hi]) => {
+                let value = u16::from_le_bytes([*lo, *hi]) as u32;
+                let mut pixel = from_masks(value, &[0x7C00, 0x03E0, 0x001F, 0x8000]);
+                pixel[3] = alpha(pixel[3]);
+                pixel
+            }
+            (false, [b, g, r]) => [*r, *g, *b, 0xFF],
+            (false, [b, g, r, a, ..]) => [*r, *g, *b, alpha(*a)],
+            _ => [0, 0, 0, 0xFF],
+        }
+    }
+
+    fn decode_qoi(&self, mut row: impl FnMut(u32, &[Rgba])) -> Result<(), ImageError> {
+        let (width, height) = (self.size.width as usize, self.size.height);
+        let mut pixels = vec![[0u8; 4]; width];
+
+        let mut offset = 14;
+        let mut index = [[0u8; 4]; 64];
+        let mut pixel = [0, 0, 0, 0xFF];
+        let mut run = 0;
+
+        for y in 0..height {
+            for out in pixels.iter_mut() {
+                if run > 0 {
+                    run -= 1;
+                } else {
+                    let op = bytes(&self.data, offset, 1)?[0];
+                    offset += 1;
+
+                    match op {
+                        0xFE => {
+                            pixel[..3].copy_from_slice(bytes(&self.data, offset, 3)?);
+                            offset += 3;
+                        }
+                        0xFF => {
+                            pixel.copy_from_slice(bytes(&self.data, offset, 4)?);
+                            offset += 4;
+                        }
+                        _ => match op >> 6 {
+                            // INDEX
+                            0 => pixel = index[op as usize],
+                            // DIFF
+                            1 => {
+                                pixel[0] =
+                                    pixel[0].wrapping_add(((op >> 4) & 0x03).wrapping_sub(2));
+                                pixel[1] =
+                                    pixel[1].wrapping_add(((op >> 2) & 0x03).wrapping_sub(2));
+                                pixel[2] = pixel[2].wrapping_add((op & 0x03).wrapping_sub(2));
+                            }
+                            // LUMA
+                            2 => {
+                                let next = bytes(&self.data, offset, 1)?[0];
+                                offset += 1;
+                                let dg = (op & 0x3F).wrapping_sub(32);
+                                let dr = dg.wrapping_add((next >> 4).wrapping_sub(8));
+                                let db = dg.wrapping_add((next & 0x0F).wrapping_sub(8));
+                                pixel[0] = pixel[0].wrapping_add(dr);
+                                pixel[1] = pixel[1].wrapping_add(dg);
+                                pixel[2] = pixel[2].wrapping_add(db);
+                            }
+                            // RUN, this pixel is the first of it
+                            _ => run = (op & 0x3F) as usize,
+                        },
+                    }
+                    index[qoi_hash(&pixel)] = pixel;
+                }
+                *out = pixel;
+            }
+
+            row(y, &pixels);
+        }
+
+        Ok(())
+    }
+}
+
+impl Image<'static> {
+    /// Reads the whole image into memory, e.g. from a file or a socket.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, ImageError> {
+        let mut data = Vec::new();
+        let mut chunk = [0u8; 512];
+        loop {
+            let read = reader.read(&mut chunk).map_err(|_| ImageError::Read)?;
+            if read == 0 {
+                break;
+            }
+            data.extend_from_slice(&chunk[..read]);
+        }
+        Image::new(data)
+    }
+}
+
+impl OriginDimensions for Image<'_> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+fn bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], ImageError> {
+    let end = offset.checked_add(len).ok_or(ImageError::Truncated)?;
+    data.get(offset..end).ok_or(ImageError::Truncated)
+}
+
+fn le16(data: &[u8], offset: usize) -> Result<u16, ImageError> {
+    let bytes = bytes(data, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn le32(data: &[u8], offset: usize) -> Result<u32, ImageError> {
+    let bytes = bytes(data, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn be32(data: &[u8], offset: usize) -> Result<u32, ImageError> {
+    Ok(le32(data, offset)?.swap_bytes())
+}
+
+// Scales each masked channel to 8 bits, a missing alpha mask means opaque
+// and any other missing mask reads as 0
+fn from_masks(value: u32, masks: &[u32; 4]) -> Rgba {
+    let channel = |mask: u32| {
+        if mask == 0 {
+            return 0;
+        }
+        let max = (1u64 << mask.count_ones()) - 1;
+        let bits = ((value & mask) >> mask.trailing_zeros()) as u64;
+        (bits * 255 / max.max(1)) as u8
+    };
+    let alpha = if masks[3] == 0 {
+        0xFF
+    } else {
+        channel(masks[3])
+    };
+    [
+        channel(masks[0]),
+        channel(masks[1]),
+        channel(masks[2]),
+        alpha,
+    ]
+}
+
+fn qoi_hash(pixel: &Rgba) -> usize {
+    let [r, g, b, a] = pixel.map(|c| c as usize);
+    (r * 3 + g * 5 + b * 7 + a * 11) % 64
+}
+
+fn parse_bmp(data: &[u8]) -> Result<(Header, Size, bool), ImageError> {
+    const BI_RGB: u32 = 0;
+    const BI_BITFIELDS: u32 = 3;
+    const BI_ALPHABITFIELDS: u32 = 6;
+
+    let pixels = le32(data, 10)? as usize;
+    let dib_size = le32(data, 14)? as usize;
+    // The old 12 byte core header isn't worth supporting
+    if dib_size < 40 {
+        return Err(ImageError::Unsupported);
+    }
+    let width = le32(data, 18)? as i32;
+    let height = le32(data, 22)? as i32;
+    // Checked before any arithmetic, a hostile header would overflow the
+    // stride otherwise. Negative heights mean top down, negative widths
+    // don't exist
+    if width <= 0
+        || height == 0
+        || width.unsigned_abs() > MAX_DIMENSION
+        || height.unsigned_abs() > MAX_DIMENSION
+    {
+        return Err(ImageError::Unsupported);
+    }
+    let bpp = le16(data, 28)?;
+    let compression = le32(data, 30)?;
+
+    let masks = match (bpp, compression) {
+        (16, BI_RGB) => [0x7C00, 0x03E0, 0x001F, 0],
+        (32, BI_RGB) => [0xFF_0000, 0xFF00, 0xFF, 0],
+        (16 | 32, BI_BITFIELDS | BI_ALPHABITFIELDS) => {
+            // Right after a 40 byte header, or part of a V4/V5 header
+            let at = 14 + 40;
+            let alpha = if dib_size >= 56 || compression == BI_ALPHABITFIELDS {
+                le32(data, at + 12)?
+            } else {
+                0
+            };
+            [
+                le32(data, at)?,
+                le32(data, at + 4)?,
+                le32(data, at + 8)?,
+                alpha,
+            ]
+        }
+        (1 | 4 | 8 | 24, BI_RGB) => [0; 4],
+        _ => return Err(ImageError::Unsupported),
+    };
+
+    let colors_used = le32(data, 46)? as usize;
+    let palette_len = match bpp {
+        1 | 4 | 8 if colors_used != 0 => colors_used,
+        1 | 4 | 8 => 1 << bpp,
+        _ => 0,
+    };
+
+    let size = Size::new(width.unsigned_abs(), height.unsigned_abs());
+    let stride = (size.width as usize)
+        .checked_mul(bpp as usize)
+        .map(|bits| bits.div_ceil(32) * 4)
+        .ok_or(ImageError::Unsupported)?;
+    let len = stride
+        .checked_mul(size.height as usize)
+        .ok_or(ImageError::Unsupported)?;
+    bytes(data, pixels, len)?;
+    let palette = dib_size.checked_add(14).ok_or(ImageError::Truncated)?;
+
+    let header = BmpHeader {
+        pixels,
+        stride,
+        bpp,
+        top_down: height < 0,
+        masks,
+        palette,
+        palette_len,
+    };
+    Ok((Header::Bmp(header), size, masks[3] != 0))
+}
+
+fn parse_tga(data: &[u8]) -> Result<(Header, Size, bool), ImageError> {
+    let header = bytes(data, 0, 18)?;
+    let (id_len, color_map, image_type) = (header[0], header[1], header[2]);
+    let (rle, gray) = match image_type {
+        2 => (false, false),
+        3 => (false, true),
+        10 => (true, false),
+        11 => (true, true),
+        // Colour mapped
+        1 | 9 => return Err(ImageError::Unsupported),
+        _ => return Err(ImageError::UnknownFormat),
+    };
+
+    let map_len = le16(header, 5)? as usize;
+    let map_entry_bits = header[7] as usize;
+    let width = le16(header, 12)?;
+    let height = le16(header, 14)?;
+    let (bpp, descriptor) = (header[16], header[17]);
+
+    let bpp_ok = match gray {
+        true => matches!(bpp, 8 | 16),
+        false => matches!(bpp, 15 | 16 | 24 | 32),
+    };
+    if !bpp_ok || width == 0 || height == 0 {
+        return Err(ImageError::UnknownFormat);
+    }
+
+    // Skip the image ID and an unused colour map
+    let mut pixels = 18 + id_len as usize;
+    if color_map != 0 {
+        pixels += map_len * map_entry_bits.div_ceil(8);
+    }
+
+    let header = TgaHeader {
+        pixels,
+        bpp,
+        rle,
+        gray,
+        top_down: descriptor & 0x20 != 0,
+        right_to_left: descriptor & 0x10 != 0,
+    };
+    let has_alpha = descriptor & 0x0F != 0;
+    Ok((
+        Header::Tga(header),
+        Size::new(width as u32, height as u32),
+        has_alpha,
+    ))
+}
+
+fn parse_qoi(data: &[u8]) -> Result<(Header, Size, bool), ImageError> {
+    let size = Size::new(be32(data, 4)?, be32(data, 8)?);
+    let channels = bytes(data, 12, 1)?[0];
+    if !matches!(channels, 3 | 4) {
+        return Err(ImageError::UnknownFormat);
+    }
+    Ok((Header::Qoi, size, channels == 4))
+}
+
+impl<B, T, C> Spd2010<B, T, C>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    /// Draws `image` with its top left corner at `top_left`, clipped to the
+    /// screen. On a decode error the rows before it stay drawn.
+    pub fn draw_image(
+        &mut self,
+        image: &Image,
+        top_left: Point,
+        options: BlitOptions,
+    ) -> Result<(), ImageError> {
+        let height = self.bounding_box().size.height as i32;
+        let blend_alpha = options.alpha && image.has_alpha();
+        let opaque = !blend_alpha && options.color_key.is_none();
+        let mut run: Vec<C> = Vec::with_capacity(image.size.width as usize);
+
+        image.decode_rows(|y, row| {
+            let y = top_left.y + y as i32;
+            if !(0..height).contains(&y) {
+                return;
+            }
+
+            if opaque {
+                let colors = row.iter().map(|&[r, g, b, _]| Rgb888::new(r, g, b).into());
+                let area =
+                    Rectangle::new(Point::new(top_left.x, y), Size::new(row.len() as u32, 1));
+                let Ok(()) = self.fill_contiguous(&area, colors);
+                return;
+            }
+
+            // Copy runs of drawn pixels, so transparency doesn't cost a
+            // per-pixel draw
+            let mut start = top_left;
+            for (x, &[r, g, b, a]) in row.iter().enumerate() {
+                let point = Point::new(top_left.x + x as i32, y);
+                let color = Rgb888::new(r, g, b);
+
+                let drawn = if options.color_key == Some(color) {
+                    None
+                } else if !blend_alpha || a == 0xFF {
+                    Some(color)
+                } else if a == 0 {
+                    None
+                } else {
+                    self.pixel(point).map(|below| blend(below.into(), color, a))
+                };
+
+                match drawn {
+                    Some(color) => {
+                        if run.is_empty() {
+                            start = point;
+                        }
+                        run.push(color.into());
+                    }
+                    None => self.blit_run(start, &mut run),
+                }
+            }
+            self.blit_run(start, &mut run);
+        })
+    }
+
+    fn blit_run(&mut self, start: Point, run: &mut Vec<C>) {
+        if run.is_empty() {
+            return;
+        }
+        let area = Rectangle::new(start, Size::new(run.len() as u32, 1));
+        let Ok(()) = self.fill_contiguous(&area, run.drain(..));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+
+    use super::*;
+    use crate::display::mock;
+
+    // A BI_RGB bitmap with a 40 byte header, `pixels` are the rows as stored
+    fn bmp(width: i32, height: i32, bpp: u16, palette: &[[u8; 4]], pixels: &[u8]) -> Vec<u8> {
+        let offset = 14 + 40 + palette.len() as u32 * 4;
+        let mut bmp = Vec::new();
+        bmp.extend_from_slice(b"BM");
+        bmp.extend_from_slice(&(offset + pixels.len() as u32).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&offset.to_le_bytes());
+        for value in [40, width, height] {
+            bmp.extend_from_slice(&value.to_le_bytes());
+        }
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&bpp.to_le_bytes());
+        let size = pixels.len() as u32;
+        for value in [0, size, 2835, 2835, palette.len() as u32, 0] {
+            bmp.extend_from_slice(&value.to_le_bytes());
+        }
+        for entry in palette {
+            bmp.extend_from_slice(entry);
+        }
+        bmp.extend_from_slice(pixels);
+        bmp
+    }
+
+    fn tga(
+        image_type: u8,
+        width: u16,
+        height: u16,
+        bpp: u8,
+        descriptor: u8,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut tga = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        tga.extend_from_slice(&width.to_le_bytes());
+        tga.extend_from_slice(&height.to_le_bytes());
+        tga.extend_from_slice(&[bpp, descriptor]);
+        tga.extend_from_slice(data);
+        tga
+    }
+
+    fn row(
+        display: &Spd2010<mock::RecordingBus, mock::NoTearing, Rgb888>,
+        y: i32,
+        width: i32,
+    ) -> Vec<Rgb888> {
+        (0..width)
+            .map(|x| display.pixel(Point::new(x, y)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        assert_eq!(Image::new(&b"BM\0\0"[..]), Err(ImageError::Truncated));
+        assert_eq!(Image::new(&b"qoif\0\0"[..]), Err(ImageError::Truncated));
+        assert_eq!(Image::new(&[0, 0, 2][..]), Err(ImageError::Truncated));
+
+        // The header is fine, the last row is missing
+        let mut data = bmp(2, 2, 24, &[], &[0; 16]);
+        data.truncate(data.len() - 8);
+        assert_eq!(Image::new(data), Err(ImageError::Truncated));
+    }
+
+    #[test]
+    fn rejects_oversize_and_negative_dimensions() {
+        for (width, height) in [
+            (i32::MAX, i32::MAX),
+            (i32::MAX, 1),
+            (1, i32::MIN),
+            (-1, 1),
+            (0, 1),
+            (1, 0),
+            (4097, 1),
+        ] {
+            let data = bmp(width, height, 32, &[], &[0; 4]);
+            assert_eq!(
+                Image::new(data),
+                Err(ImageError::Unsupported),
+                "{width}x{height}"
+            );
+        }
+
+        let data = tga(2, 5000, 1, 24, 0, &[]);
+        assert_eq!(Image::new(data), Err(ImageError::Unsupported));
+        let mut qoi = b"qoif".to_vec();
+        qoi.extend_from_slice(&u32::MAX.to_be_bytes());
+        qoi.extend_from_slice(&u32::MAX.to_be_bytes());
+        qoi.extend_from_slice(&[4, 0]);
+        assert_eq!(Image::new(qoi), Err(ImageError::Unsupported));
+    }
+
+    #[test]
+    fn draws_palettised_bmp_bottom_up() {
+        // Entries are B, G, R, reserved
+        let palette = [[0, 0, 0xFF, 0], [0xFF, 0, 0, 0]];
+        // Two 4 bit pixels per byte, rows padded to 4 bytes, bottom row first
+        let pixels = [0x01, 0, 0, 0, 0x10, 0, 0, 0];
+        let image = Image::new(bmp(2, 2, 4, &palette, &pixels)).unwrap();
+        assert_eq!(image.format(), ImageFormat::Bmp);
+        assert_eq!(image.size(), Size::new(2, 2));
+
+        let mut display = mock::display::<Rgb888>();
+        display
+            .draw_image(&image, Point::zero(), BlitOptions::default())
+            .unwrap();
+        assert_eq!(row(&display, 0, 2), [Rgb888::BLUE, Rgb888::RED]);
+        assert_eq!(row(&display, 1, 2), [Rgb888::RED, Rgb888::BLUE]);
+    }
+
+    #[test]
+    fn draws_tga_bottom_up() {
+        // B, G, R and the bottom row first
+        let data = [0, 0, 0xFF, 0, 0xFF, 0, 0xFF, 0, 0, 0xFF, 0xFF, 0xFF];
+        let image = Image::new(tga(2, 2, 2, 24, 0, &data)).unwrap();
+        assert_eq!(image.format(), ImageFormat::Tga);
+        assert!(!image.has_alpha());
+
+        let mut display = mock::display::<Rgb888>();
+        display
+            .draw_image(&image, Point::zero(), BlitOptions::default())
+            .unwrap();
+        assert_eq!(row(&display, 0, 2), [Rgb888::BLUE, Rgb888::WHITE]);
+        assert_eq!(row(&display, 1, 2), [Rgb888::RED, Rgb888::GREEN]);
+    }
+
+    #[test]
+    fn rle_tga_packets_run_across_rows() {
+        // Four times red, one more than the first row holds, then two raw
+        // pixels
+        let data = [0x83, 0, 0, 0xFF, 0x01, 0, 0xFF, 0, 0xFF, 0, 0];
+        let image = Image::new(tga(10, 3, 2, 24, 0x20, &data)).unwrap();
+
+        let mut display = mock::display::<Rgb888>();
+        display
+            .draw_image(&image, Point::zero(), BlitOptions::default())
+            .unwrap();
+        assert_eq!(row(&display, 0, 3), [Rgb888::RED; 3]);
+        assert_eq!(
+            row(&display, 1, 3),
+            [Rgb888::RED, Rgb888::GREEN, Rgb888::BLUE]
+        );
+
+        // A packet promising more pixels than the data has
+        let image = Image::new(tga(10, 3, 2, 24, 0x20, &data[..8])).unwrap();
+        let result = display.draw_image(&image, Point::zero(), BlitOptions::default());
+        assert_eq!(result, Err(ImageError::Truncated));
+    }
+
+    #[test]
+    fn blends_alpha_only_when_asked() {
+        let background = Rgb888::new(40, 80, 120);
+        // Half transparent white, then fully transparent red
+        let data = [0xFF, 0xFF, 0xFF, 0x80, 0, 0, 0xFF, 0];
+        let image = Image::new(tga(2, 2, 1, 32, 0x28, &data)).unwrap();
+        assert!(image.has_alpha());
+
+        let mut display = mock::display::<Rgb888>();
+        display.clear(background).unwrap();
+        let options = BlitOptions::default().with_alpha();
+        display.draw_image(&image, Point::zero(), options).unwrap();
+        // (255 * 128 + 40 * 127 + 127) / 255 and so on
+        assert_eq!(
+            row(&display, 0, 2),
+            [Rgb888::new(148, 168, 188), background]
+        );
+
+        display
+            .draw_image(&image, Point::zero(), BlitOptions::default())
+            .unwrap();
+        assert_eq!(row(&display, 0, 2), [Rgb888::WHITE, Rgb888::RED]);
+    }
+
+    #[test]
+    fn color_key_pixels_are_skipped() {
+        let data = [0, 0, 0xFF, 0xFF, 0, 0xFF, 0xFF, 0, 0];
+        let image = Image::new(tga(2, 3, 1, 24, 0, &data)).unwrap();
+
+        let mut display = mock::display::<Rgb888>();
+        display.clear(Rgb888::GREEN).unwrap();
+        let options = BlitOptions::default().with_color_key(Rgb888::MAGENTA);
+        display.draw_image(&image, Point::zero(), options).unwrap();
+        assert_eq!(
+            row(&display, 0, 3),
+            [Rgb888::RED, Rgb888::GREEN, Rgb888::BLUE]
+        );
+    }
+
+    #[test]
+    fn missing_masks_read_as_zero() {
+        let masks = [0xFF_0000, 0, 0xFF, 0];
+        assert_eq!(from_masks(0x00AA_33CC, &masks), [0xAA, 0, 0xCC, 0xFF]);
+        assert_eq!(from_masks(u32::MAX, &[0; 4]), [0, 0, 0, 0xFF]);
+        // Five bit channels stretch to the full range
+        let rgb555 = [0x7C00, 0x03E0, 0x001F, 0];
+        assert_eq!(from_masks(0x7FFF, &rgb555), [0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn draws_bitfield_bmp_without_a_green_mask() {
+        let mut bmp = Vec::new();
+        bmp.extend_from_slice(b"BM");
+        bmp.extend_from_slice(&70u32.to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&66u32.to_le_bytes());
+        for value in [40u32, 1, 1] {
+            bmp.extend_from_slice(&value.to_le_bytes());
+        }
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&32u16.to_le_bytes());
+        // BI_BITFIELDS, size, resolution, palette
+        for value in [3u32, 4, 2835, 2835, 0, 0] {
+            bmp.extend_from_slice(&value.to_le_bytes());
+        }
+        for mask in [0xFF_0000u32, 0, 0xFF] {
+            bmp.extend_from_slice(&mask.to_le_bytes());
+        }
+        bmp.extend_from_slice(&0x00AA_33CCu32.to_le_bytes());
+
+        let image = Image::new(&bmp[..]).unwrap();
+        let mut display = mock::display::<Rgb888>();
+        let point = Point::new(100, 100);
+        display
+            .draw_image(&image, point, BlitOptions::default())
+            .unwrap();
+        assert_eq!(display.pixel(point), Some(Rgb888::new(0xAA, 0, 0xCC)));
+    }
+}
@@ -1,405 +1,407 @@
-// [(cmd, delay, data)]
-pub const LCD_INIT_CMD: &[(u8, u8, &[u8])] = &[
-    (0xFF, 0, &[0x20, 0x10, 0x10]),
-    (0x0C, 0, &[0x11]),
-    (0x10, 0, &[0x02]),
-    (0x11, 0, &[0x11]),
-    (0x15, 0, &[0x42]),
-    (0x16, 0, &[0x11]),
-    (0x1A, 0, &[0x02]),
-    (0x1B, 0, &[0x11]),
-    (0x61, 0, &[0x80]),
-    (0x62, 0, &[0x80]),
-    (0x54, 0, &[0x44]),
-    (0x58, 0, &[0x88]),
-    (0x5C, 0, &[0xcc]),
-    (0xFF, 0, &[0x20, 0x10, 0x10]),
-    (0x20, 0, &[0x80]),
-    (0x21, 0, &[0x81]),
-    (0x22, 0, &[0x31]),
-    (0x23, 0, &[0x20]),
-    (0x24, 0, &[0x11]),
-    (0x25, 0, &[0x11]),
-    (0x26, 0, &[0x12]),
-    (0x27, 0, &[0x12]),
-    (0x30, 0, &[0x80]),
-    (0x31, 0, &[0x81]),
-    (0x32, 0, &[0x31]),
-    (0x33, 0, &[0x20]),
-    (0x34, 0, &[0x11]),
-    (0x35, 0, &[0x11]),
-    (0x36, 0, &[0x12]),
-    (0x37, 0, &[0x12]),
-    (0xFF, 0, &[0x20, 0x10, 0x10]),
-    (0x41, 0, &[0x11]),
-    (0x42, 0, &[0x22]),
-    (0x43, 0, &[0x33]),
-    (0x49, 0, &[0x11]),
-    (0x4A, 0, &[0x22]),
-    (0x4B, 0, &[0x33]),
-    (0xFF, 0, &[0x20, 0x10, 0x15]),
-    (0x00, 0, &[0x00]),
-    (0x01, 0, &[0x00]),
-    (0x02, 0, &[0x00]),
-    (0x03, 0, &[0x00]),
-    (0x04, 0, &[0x10]),
-    (0x05, 0, &[0x0C]),
-    (0x06, 0, &[0x23]),
-    (0x07, 0, &[0x22]),
-    (0x08, 0, &[0x21]),
-    (0x09, 0, &[0x20]),
-    (0x0A, 0, &[0x33]),
-    (0x0B, 0, &[0x32]),
-    (0x0C, 0, &[0x34]),
-    (0x0D, 0, &[0x35]),
-    (0x0E, 0, &[0x01]),
-    (0x0F, 0, &[0x01]),
-    (0x20, 0, &[0x00]),
-    (0x21, 0, &[0x00]),
-    (0x22, 0, &[0x00]),
-    (0x23, 0, &[0x00]),
-    (0x24, 0, &[0x0C]),
-    (0x25, 0, &[0x10]),
-    (0x26, 0, &[0x20]),
-    (0x27, 0, &[0x21]),
-    (0x28, 0, &[0x22]),
-    (0x29, 0, &[0x23]),
-    (0x2A, 0, &[0x33]),
-    (0x2B, 0, &[0x32]),
-    (0x2C, 0, &[0x34]),
-    (0x2D, 0, &[0x35]),
-    (0x2E, 0, &[0x01]),
-    (0x2F, 0, &[0x01]),
-    (0xFF, 0, &[0x20, 0x10, 0x16]),
-    (0x00, 0, &[0x00]),
-    (0x01, 0, &[0x00]),
-    (0x02, 0, &[0x00]),
-    (0x03, 0, &[0x00]),
-    (0x04, 0, &[0x08]),
-    (0x05, 0, &[0x04]),
-    (0x06, 0, &[0x19]),
-    (0x07, 0, &[0x18]),
-    (0x08, 0, &[0x17]),
-    (0x09, 0, &[0x16]),
-    (0x0A, 0, &[0x33]),
-    (0x0B, 0, &[0x32]),
-    (0x0C, 0, &[0x34]),
-    (0x0D, 0, &[0x35]),
-    (0x0E, 0, &[0x01]),
-    (0x0F, 0, &[0x01]),
-    (0x20, 0, &[0x00]),
-    (0x21, 0, &[0x00]),
-    (0x22, 0, &[0x00]),
-    (0x23, 0, &[0x00]),
-    (0x24, 0, &[0x04]),
-    (0x25, 0, &[0x08]),
-    (0x26, 0, &[0x16]),
-    (0x27, 0, &[0x17]),
-    (0x28, 0, &[0x18]),
-    (0x29, 0, &[0x19]),
-    (0x2A, 0, &[0x33]),
-    (0x2B, 0, &[0x32]),
-    (0x2C, 0, &[0x34]),
-    (0x2D, 0, &[0x35]),
-    (0x2E, 0, &[0x01]),
-    (0x2F, 0, &[0x01]),
-    (0xFF, 0, &[0x20, 0x10, 0x12]),
-    (0x00, 0, &[0x99]),
-    (0x2A, 0, &[0x28]),
-    (0x2B, 0, &[0x0f]),
-    (0x2C, 0, &[0x16]),
-    (0x2D, 0, &[0x28]),
-    (0x2E, 0, &[0x0f]),
-    (0xFF, 0, &[0x20, 0x10, 0xA0]),
-    (0x08, 0, &[0xdc]),
-    (0xFF, 0, &[0x20, 0x10, 0x45]),
-    (0x01, 0, &[0x9C]),
-    (0x03, 0, &[0x9C]),
-    (0xFF, 0, &[0x20, 0x10, 0x42]),
-    (0x05, 0, &[0x2c]),
-    (0xFF, 0, &[0x20, 0x10, 0x11]),
-    (0x50, 0, &[0x01]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0x2A, 0, &[0x00, 0x00, 0x01, 0x9B]),
-    (0x2B, 0, &[0x00, 0x00, 0x01, 0x9B]),
-    (0xFF, 0, &[0x20, 0x10, 0x40]),
-    (0x86, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x12]),
-    (0x0D, 0, &[0x66]),
-    (0xFF, 0, &[0x20, 0x10, 0x17]),
-    (0x39, 0, &[0x3c]),
-    (0xff, 0, &[0x20, 0x10, 0x31]),
-    (0x38, 0, &[0x03]),
-    (0x39, 0, &[0xf0]),
-    (0x36, 0, &[0x03]),
-    (0x37, 0, &[0xe8]),
-    (0x34, 0, &[0x03]),
-    (0x35, 0, &[0xCF]),
-    (0x32, 0, &[0x03]),
-    (0x33, 0, &[0xBA]),
-    (0x30, 0, &[0x03]),
-    (0x31, 0, &[0xA2]),
-    (0x2e, 0, &[0x03]),
-    (0x2f, 0, &[0x95]),
-    (0x2c, 0, &[0x03]),
-    (0x2d, 0, &[0x7e]),
-    (0x2a, 0, &[0x03]),
-    (0x2b, 0, &[0x62]),
-    (0x28, 0, &[0x03]),
-    (0x29, 0, &[0x44]),
-    (0x26, 0, &[0x02]),
-    (0x27, 0, &[0xfc]),
-    (0x24, 0, &[0x02]),
-    (0x25, 0, &[0xd0]),
-    (0x22, 0, &[0x02]),
-    (0x23, 0, &[0x98]),
-    (0x20, 0, &[0x02]),
-    (0x21, 0, &[0x6f]),
-    (0x1e, 0, &[0x02]),
-    (0x1f, 0, &[0x32]),
-    (0x1c, 0, &[0x01]),
-    (0x1d, 0, &[0xf6]),
-    (0x1a, 0, &[0x01]),
-    (0x1b, 0, &[0xb8]),
-    (0x18, 0, &[0x01]),
-    (0x19, 0, &[0x6E]),
-    (0x16, 0, &[0x01]),
-    (0x17, 0, &[0x41]),
-    (0x14, 0, &[0x00]),
-    (0x15, 0, &[0xfd]),
-    (0x12, 0, &[0x00]),
-    (0x13, 0, &[0xCF]),
-    (0x10, 0, &[0x00]),
-    (0x11, 0, &[0x98]),
-    (0x0e, 0, &[0x00]),
-    (0x0f, 0, &[0x89]),
-    (0x0c, 0, &[0x00]),
-    (0x0d, 0, &[0x79]),
-    (0x0a, 0, &[0x00]),
-    (0x0b, 0, &[0x67]),
-    (0x08, 0, &[0x00]),
-    (0x09, 0, &[0x55]),
-    (0x06, 0, &[0x00]),
-    (0x07, 0, &[0x3F]),
-    (0x04, 0, &[0x00]),
-    (0x05, 0, &[0x28]),
-    (0x02, 0, &[0x00]),
-    (0x03, 0, &[0x0E]),
-    (0xff, 0, &[0x20, 0x10, 0x00]),
-    (0xff, 0, &[0x20, 0x10, 0x32]),
-    (0x38, 0, &[0x03]),
-    (0x39, 0, &[0xf0]),
-    (0x36, 0, &[0x03]),
-    (0x37, 0, &[0xe8]),
-    (0x34, 0, &[0x03]),
-    (0x35, 0, &[0xCF]),
-    (0x32, 0, &[0x03]),
-    (0x33, 0, &[0xBA]),
-    (0x30, 0, &[0x03]),
-    (0x31, 0, &[0xA2]),
-    (0x2e, 0, &[0x03]),
-    (0x2f, 0, &[0x95]),
-    (0x2c, 0, &[0x03]),
-    (0x2d, 0, &[0x7e]),
-    (0x2a, 0, &[0x03]),
-    (0x2b, 0, &[0x62]),
-    (0x28, 0, &[0x03]),
-    (0x29, 0, &[0x44]),
-    (0x26, 0, &[0x02]),
-    (0x27, 0, &[0xfc]),
-    (0x24, 0, &[0x02]),
-    (0x25, 0, &[0xd0]),
-    (0x22, 0, &[0x02]),
-    (0x23, 0, &[0x98]),
-    (0x20, 0, &[0x02]),
-    (0x21, 0, &[0x6f]),
-    (0x1e, 0, &[0x02]),
-    (0x1f, 0, &[0x32]),
-    (0x1c, 0, &[0x01]),
-    (0x1d, 0, &[0xf6]),
-    (0x1a, 0, &[0x01]),
-    (0x1b, 0, &[0xb8]),
-    (0x18, 0, &[0x01]),
-    (0x19, 0, &[0x6E]),
-    (0x16, 0, &[0x01]),
-    (0x17, 0, &[0x41]),
-    (0x14, 0, &[0x00]),
-    (0x15, 0, &[0xfd]),
-    (0x12, 0, &[0x00]),
-    (0x13, 0, &[0xCF]),
-    (0x10, 0, &[0x00]),
-    (0x11, 0, &[0x98]),
-    (0x0e, 0, &[0x00]),
-    (0x0f, 0, &[0x89]),
-    (0x0c, 0, &[0x00]),
-    (0x0d, 0, &[0x79]),
-    (0x0a, 0, &[0x00]),
-    (0x0b, 0, &[0x67]),
-    (0x08, 0, &[0x00]),
-    (0x09, 0, &[0x55]),
-    (0x06, 0, &[0x00]),
-    (0x07, 0, &[0x3F]),
-    (0x04, 0, &[0x00]),
-    (0x05, 0, &[0x28]),
-    (0x02, 0, &[0x00]),
-    (0x03, 0, &[0x0E]),
-    (0xff, 0, &[0x20, 0x10, 0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x11]),
-    (0x60, 0, &[0x01]),
-    (0x65, 0, &[0x03]),
-    (0x66, 0, &[0x38]),
-    (0x67, 0, &[0x04]),
-    (0x68, 0, &[0x34]),
-    (0x69, 0, &[0x03]),
-    (0x61, 0, &[0x03]),
-    (0x62, 0, &[0x38]),
-    (0x63, 0, &[0x04]),
-    (0x64, 0, &[0x34]),
-    (0x0A, 0, &[0x11]),
-    (0x0B, 0, &[0x20]),
-    (0x0c, 0, &[0x20]),
-    (0x55, 0, &[0x06]),
-    (0xFF, 0, &[0x20, 0x10, 0x42]),
-    (0x05, 0, &[0x3D]),
-    (0x06, 0, &[0x03]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x12]),
-    (0x1F, 0, &[0xDC]),
-    (0xff, 0, &[0x20, 0x10, 0x17]),
-    (0x11, 0, &[0xAA]),
-    (0x16, 0, &[0x12]),
-    (0x0B, 0, &[0xC3]),
-    (0x10, 0, &[0x0E]),
-    (0x14, 0, &[0xAA]),
-    (0x18, 0, &[0xA0]),
-    (0x1A, 0, &[0x80]),
-    (0x1F, 0, &[0x80]),
-    (0xff, 0, &[0x20, 0x10, 0x11]),
-    (0x30, 0, &[0xEE]),
-    (0xff, 0, &[0x20, 0x10, 0x12]),
-    (0x15, 0, &[0x0F]),
-    (0xff, 0, &[0x20, 0x10, 0x2D]),
-    (0x01, 0, &[0x3E]),
-    (0xff, 0, &[0x20, 0x10, 0x40]),
-    (0x83, 0, &[0xC4]),
-    (0xFF, 0, &[0x20, 0x10, 0x12]),
-    (0x00, 0, &[0xCC]),
-    (0x36, 0, &[0xA0]), // 1010 0000
-    (0x2A, 0, &[0x2D]),
-    (0x2B, 0, &[0x1e]),
-    (0x2C, 0, &[0x26]),
-    (0x2D, 0, &[0x2D]),
-    (0x2E, 0, &[0x1e]),
-    (0x1F, 0, &[0xE6]),
-    (0xFF, 0, &[0x20, 0x10, 0xA0]),
-    (0x08, 0, &[0xE6]),
-    (0xFF, 0, &[0x20, 0x10, 0x12]),
-    (0x10, 0, &[0x0F]),
-    (0xFF, 0, &[0x20, 0x10, 0x18]),
-    (0x01, 0, &[0x01]),
-    (0x00, 0, &[0x1E]),
-    (0xFF, 0, &[0x20, 0x10, 0x43]),
-    (0x03, 0, &[0x04]),
-    (0xFF, 0, &[0x20, 0x10, 0x18]),
-    (0x3A, 0, &[0x01]),
-    (0xFF, 0, &[0x20, 0x10, 0x50]),
-    (0x05, 0, &[0x08]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x50]),
-    (0x00, 0, &[0xA6]),
-    (0x01, 0, &[0xA6]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x50]),
-    (0x08, 0, &[0x55]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x10]),
-    (0x0B, 0, &[0x43]),
-    (0x0C, 0, &[0x12]),
-    (0x10, 0, &[0x01]),
-    (0x11, 0, &[0x12]),
-    (0x15, 0, &[0x00]),
-    (0x16, 0, &[0x00]),
-    (0x1A, 0, &[0x00]),
-    (0x1B, 0, &[0x00]),
-    (0x61, 0, &[0x00]),
-    (0x62, 0, &[0x00]),
-    (0x51, 0, &[0x11]),
-    (0x55, 0, &[0x55]),
-    (0x58, 0, &[0x00]),
-    (0x5C, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x10]),
-    (0x20, 0, &[0x81]),
-    (0x21, 0, &[0x82]),
-    (0x22, 0, &[0x72]),
-    (0x30, 0, &[0x00]),
-    (0x31, 0, &[0x00]),
-    (0x32, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x10]),
-    (0x44, 0, &[0x44]),
-    (0x45, 0, &[0x55]),
-    (0x46, 0, &[0x66]),
-    (0x47, 0, &[0x77]),
-    (0x49, 0, &[0x00]),
-    (0x4A, 0, &[0x00]),
-    (0x4B, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x17]),
-    (0x37, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x15]),
-    (0x04, 0, &[0x08]),
-    (0x05, 0, &[0x04]),
-    (0x06, 0, &[0x1C]),
-    (0x07, 0, &[0x1A]),
-    (0x08, 0, &[0x18]),
-    (0x09, 0, &[0x16]),
-    (0x24, 0, &[0x05]),
-    (0x25, 0, &[0x09]),
-    (0x26, 0, &[0x17]),
-    (0x27, 0, &[0x19]),
-    (0x28, 0, &[0x1B]),
-    (0x29, 0, &[0x1D]),
-    (0xFF, 0, &[0x20, 0x10, 0x16]),
-    (0x04, 0, &[0x09]),
-    (0x05, 0, &[0x05]),
-    (0x06, 0, &[0x1D]),
-    (0x07, 0, &[0x1B]),
-    (0x08, 0, &[0x19]),
-    (0x09, 0, &[0x17]),
-    (0x24, 0, &[0x04]),
-    (0x25, 0, &[0x08]),
-    (0x26, 0, &[0x16]),
-    (0x27, 0, &[0x18]),
-    (0x28, 0, &[0x1A]),
-    (0x29, 0, &[0x1C]),
-    (0xFF, 0, &[0x20, 0x10, 0x18]),
-    (0x1F, 0, &[0x02]),
-    (0xFF, 0, &[0x20, 0x10, 0x11]),
-    (0x15, 0, &[0x99]),
-    (0x16, 0, &[0x99]),
-    (0x1C, 0, &[0x88]),
-    (0x1D, 0, &[0x88]),
-    (0x1E, 0, &[0x88]),
-    (0x13, 0, &[0xf0]),
-    (0x14, 0, &[0x34]),
-    (0xFF, 0, &[0x20, 0x10, 0x12]),
-    (0x12, 0, &[0x89]),
-    (0x06, 0, &[0x06]),
-    (0x18, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x11]),
-    (0x0A, 0, &[0x00]),
-    (0x0B, 0, &[0xF0]),
-    (0x0c, 0, &[0xF0]),
-    (0x6A, 0, &[0x10]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x11]),
-    (0x08, 0, &[0x70]),
-    (0x09, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0x35, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x12]),
-    (0x21, 0, &[0x70]),
-    (0xFF, 0, &[0x20, 0x10, 0x2D]),
-    (0x02, 0, &[0x00]),
-    (0xFF, 0, &[0x20, 0x10, 0x00]),
-    (0x11, 120, &[0x00]),
+use super::init_sequence::InitCommand;
+
+// Vendor sequence for the Waveshare 1.46" panel
+pub const LCD_INIT_CMD: &[InitCommand<'static>] = &[
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x10]),
+    InitCommand::new(0x0C, 0, &[0x11]),
+    InitCommand::new(0x10, 0, &[0x02]),
+    InitCommand::new(0x11, 0, &[0x11]),
+    InitCommand::new(0x15, 0, &[0x42]),
+    InitCommand::new(0x16, 0, &[0x11]),
+    InitCommand::new(0x1A, 0, &[0x02]),
+    InitCommand::new(0x1B, 0, &[0x11]),
+    InitCommand::new(0x61, 0, &[0x80]),
+    InitCommand::new(0x62, 0, &[0x80]),
+    InitCommand::new(0x54, 0, &[0x44]),
+    InitCommand::new(0x58, 0, &[0x88]),
+    InitCommand::new(0x5C, 0, &[0xcc]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x10]),
+    InitCommand::new(0x20, 0, &[0x80]),
+    InitCommand::new(0x21, 0, &[0x81]),
+    InitCommand::new(0x22, 0, &[0x31]),
+    InitCommand::new(0x23, 0, &[0x20]),
+    InitCommand::new(0x24, 0, &[0x11]),
+    InitCommand::new(0x25, 0, &[0x11]),
+    InitCommand::new(0x26, 0, &[0x12]),
+    InitCommand::new(0x27, 0, &[0x12]),
+    InitCommand::new(0x30, 0, &[0x80]),
+    InitCommand::new(0x31, 0, &[0x81]),
+    InitCommand::new(0x32, 0, &[0x31]),
+    InitCommand::new(0x33, 0, &[0x20]),
+    InitCommand::new(0x34, 0, &[0x11]),
+    InitCommand::new(0x35, 0, &[0x11]),
+    InitCommand::new(0x36, 0, &[0x12]),
+    InitCommand::new(0x37, 0, &[0x12]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x10]),
+    InitCommand::new(0x41, 0, &[0x11]),
+    InitCommand::new(0x42, 0, &[0x22]),
+    InitCommand::new(0x43, 0, &[0x33]),
+    InitCommand::new(0x49, 0, &[0x11]),
+    InitCommand::new(0x4A, 0, &[0x22]),
+    InitCommand::new(0x4B, 0, &[0x33]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x15]),
+    InitCommand::new(0x00, 0, &[0x00]),
+    InitCommand::new(0x01, 0, &[0x00]),
+    InitCommand::new(0x02, 0, &[0x00]),
+    InitCommand::new(0x03, 0, &[0x00]),
+    InitCommand::new(0x04, 0, &[0x10]),
+    InitCommand::new(0x05, 0, &[0x0C]),
+    InitCommand::new(0x06, 0, &[0x23]),
+    InitCommand::new(0x07, 0, &[0x22]),
+    InitCommand::new(0x08, 0, &[0x21]),
+    InitCommand::new(0x09, 0, &[0x20]),
+    InitCommand::new(0x0A, 0, &[0x33]),
+    InitCommand::new(0x0B, 0, &[0x32]),
+    InitCommand::new(0x0C, 0, &[0x34]),
+    InitCommand::new(0x0D, 0, &[0x35]),
+    InitCommand::new(0x0E, 0, &[0x01]),
+    InitCommand::new(0x0F, 0, &[0x01]),
+    InitCommand::new(0x20, 0, &[0x00]),
+    InitCommand::new(0x21, 0, &[0x00]),
+    InitCommand::new(0x22, 0, &[0x00]),
+    InitCommand::new(0x23, 0, &[0x00]),
+    InitCommand::new(0x24, 0, &[0x0C]),
+    InitCommand::new(0x25, 0, &[0x10]),
+    InitCommand::new(0x26, 0, &[0x20]),
+    InitCommand::new(0x27, 0, &[0x21]),
+    InitCommand::new(0x28, 0, &[0x22]),
+    InitCommand::new(0x29, 0, &[0x23]),
+    InitCommand::new(0x2A, 0, &[0x33]),
+    InitCommand::new(0x2B, 0, &[0x32]),
+    InitCommand::new(0x2C, 0, &[0x34]),
+    InitCommand::new(0x2D, 0, &[0x35]),
+    InitCommand::new(0x2E, 0, &[0x01]),
+    InitCommand::new(0x2F, 0, &[0x01]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x16]),
+    InitCommand::new(0x00, 0, &[0x00]),
+    InitCommand::new(0x01, 0, &[0x00]),
+    InitCommand::new(0x02, 0, &[0x00]),
+    InitCommand::new(0x03, 0, &[0x00]),
+    InitCommand::new(0x04, 0, &[0x08]),
+    InitCommand::new(0x05, 0, &[0x04]),
+    InitCommand::new(0x06, 0, &[0x19]),
+    InitCommand::new(0x07, 0, &[0x18]),
+    InitCommand::new(0x08, 0, &[0x17]),
+    InitCommand::new(0x09, 0, &[0x16]),
+    InitCommand::new(0x0A, 0, &[0x33]),
+    InitCommand::new(0x0B, 0, &[0x32]),
+    InitCommand::new(0x0C, 0, &[0x34]),
+    InitCommand::new(0x0D, 0, &[0x35]),
+    InitCommand::new(0x0E, 0, &[0x01]),
+    InitCommand::new(0x0F, 0, &[0x01]),
+    InitCommand::new(0x20, 0, &[0x00]),
+    InitCommand::new(0x21, 0, &[0x00]),
+    InitCommand::new(0x22, 0, &[0x00]),
+    InitCommand::new(0x23, 0, &[0x00]),
+    InitCommand::new(0x24, 0, &[0x04]),
+    InitCommand::new(0x25, 0, &[0x08]),
+    InitCommand::new(0x26, 0, &[0x16]),
+    InitCommand::new(0x27, 0, &[0x17]),
+    InitCommand::new(0x28, 0, &[0x18]),
+    InitCommand::new(0x29, 0, &[0x19]),
+    InitCommand::new(0x2A, 0, &[0x33]),
+    InitCommand::new(0x2B, 0, &[0x32]),
+    InitCommand::new(0x2C, 0, &[0x34]),
+    InitCommand::new(0x2D, 0, &[0x35]),
+    InitCommand::new(0x2E, 0, &[0x01]),
+    InitCommand::new(0x2F, 0, &[0x01]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x00, 0, &[0x99]),
+    InitCommand::new(0x2A, 0, &[0x28]),
+    InitCommand::new(0x2B, 0, &[0x0f]),
+    InitCommand::new(0x2C, 0, &[0x16]),
+    InitCommand::new(0x2D, 0, &[0x28]),
+    InitCommand::new(0x2E, 0, &[0x0f]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0xA0]),
+    InitCommand::new(0x08, 0, &[0xdc]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x45]),
+    InitCommand::new(0x01, 0, &[0x9C]),
+    InitCommand::new(0x03, 0, &[0x9C]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x42]),
+    InitCommand::new(0x05, 0, &[0x2c]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x11]),
+    InitCommand::new(0x50, 0, &[0x01]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0x2A, 0, &[0x00, 0x00, 0x01, 0x9B]),
+    InitCommand::new(0x2B, 0, &[0x00, 0x00, 0x01, 0x9B]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x40]),
+    InitCommand::new(0x86, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x0D, 0, &[0x66]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x17]),
+    InitCommand::new(0x39, 0, &[0x3c]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x31]),
+    InitCommand::new(0x38, 0, &[0x03]),
+    InitCommand::new(0x39, 0, &[0xf0]),
+    InitCommand::new(0x36, 0, &[0x03]),
+    InitCommand::new(0x37, 0, &[0xe8]),
+    InitCommand::new(0x34, 0, &[0x03]),
+    InitCommand::new(0x35, 0, &[0xCF]),
+    InitCommand::new(0x32, 0, &[0x03]),
+    InitCommand::new(0x33, 0, &[0xBA]),
+    InitCommand::new(0x30, 0, &[0x03]),
+    InitCommand::new(0x31, 0, &[0xA2]),
+    InitCommand::new(0x2e, 0, &[0x03]),
+    InitCommand::new(0x2f, 0, &[0x95]),
+    InitCommand::new(0x2c, 0, &[0x03]),
+    InitCommand::new(0x2d, 0, &[0x7e]),
+    InitCommand::new(0x2a, 0, &[0x03]),
+    InitCommand::new(0x2b, 0, &[0x62]),
+    InitCommand::new(0x28, 0, &[0x03]),
+    InitCommand::new(0x29, 0, &[0x44]),
+    InitCommand::new(0x26, 0, &[0x02]),
+    InitCommand::new(0x27, 0, &[0xfc]),
+    InitCommand::new(0x24, 0, &[0x02]),
+    InitCommand::new(0x25, 0, &[0xd0]),
+    InitCommand::new(0x22, 0, &[0x02]),
+    InitCommand::new(0x23, 0, &[0x98]),
+    InitCommand::new(0x20, 0, &[0x02]),
+    InitCommand::new(0x21, 0, &[0x6f]),
+    InitCommand::new(0x1e, 0, &[0x02]),
+    InitCommand::new(0x1f, 0, &[0x32]),
+    InitCommand::new(0x1c, 0, &[0x01]),
+    InitCommand::new(0x1d, 0, &[0xf6]),
+    InitCommand::new(0x1a, 0, &[0x01]),
+    InitCommand::new(0x1b, 0, &[0xb8]),
+    InitCommand::new(0x18, 0, &[0x01]),
+    InitCommand::new(0x19, 0, &[0x6E]),
+    InitCommand::new(0x16, 0, &[0x01]),
+    InitCommand::new(0x17, 0, &[0x41]),
+    InitCommand::new(0x14, 0, &[0x00]),
+    InitCommand::new(0x15, 0, &[0xfd]),
+    InitCommand::new(0x12, 0, &[0x00]),
+    InitCommand::new(0x13, 0, &[0xCF]),
+    InitCommand::new(0x10, 0, &[0x00]),
+    InitCommand::new(0x11, 0, &[0x98]),
+    InitCommand::new(0x0e, 0, &[0x00]),
+    InitCommand::new(0x0f, 0, &[0x89]),
+    InitCommand::new(0x0c, 0, &[0x00]),
+    InitCommand::new(0x0d, 0, &[0x79]),
+    InitCommand::new(0x0a, 0, &[0x00]),
+    InitCommand::new(0x0b, 0, &[0x67]),
+    InitCommand::new(0x08, 0, &[0x00]),
+    InitCommand::new(0x09, 0, &[0x55]),
+    InitCommand::new(0x06, 0, &[0x00]),
+    InitCommand::new(0x07, 0, &[0x3F]),
+    InitCommand::new(0x04, 0, &[0x00]),
+    InitCommand::new(0x05, 0, &[0x28]),
+    InitCommand::new(0x02, 0, &[0x00]),
+    InitCommand::new(0x03, 0, &[0x0E]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x32]),
+    InitCommand::new(0x38, 0, &[0x03]),
+    InitCommand::new(0x39, 0, &[0xf0]),
+    InitCommand::new(0x36, 0, &[0x03]),
+    InitCommand::new(0x37, 0, &[0xe8]),
+    InitCommand::new(0x34, 0, &[0x03]),
+    InitCommand::new(0x35, 0, &[0xCF]),
+    InitCommand::new(0x32, 0, &[0x03]),
+    InitCommand::new(0x33, 0, &[0xBA]),
+    InitCommand::new(0x30, 0, &[0x03]),
+    InitCommand::new(0x31, 0, &[0xA2]),
+    InitCommand::new(0x2e, 0, &[0x03]),
+    InitCommand::new(0x2f, 0, &[0x95]),
+    InitCommand::new(0x2c, 0, &[0x03]),
+    InitCommand::new(0x2d, 0, &[0x7e]),
+    InitCommand::new(0x2a, 0, &[0x03]),
+    InitCommand::new(0x2b, 0, &[0x62]),
+    InitCommand::new(0x28, 0, &[0x03]),
+    InitCommand::new(0x29, 0, &[0x44]),
+    InitCommand::new(0x26, 0, &[0x02]),
+    InitCommand::new(0x27, 0, &[0xfc]),
+    InitCommand::new(0x24, 0, &[0x02]),
+    InitCommand::new(0x25, 0, &[0xd0]),
+    InitCommand::new(0x22, 0, &[0x02]),
+    InitCommand::new(0x23, 0, &[0x98]),
+    InitCommand::new(0x20, 0, &[0x02]),
+    InitCommand::new(0x21, 0, &[0x6f]),
+    InitCommand::new(0x1e, 0, &[0x02]),
+    InitCommand::new(0x1f, 0, &[0x32]),
+    InitCommand::new(0x1c, 0, &[0x01]),
+    InitCommand::new(0x1d, 0, &[0xf6]),
+    InitCommand::new(0x1a, 0, &[0x01]),
+    InitCommand::new(0x1b, 0, &[0xb8]),
+    InitCommand::new(0x18, 0, &[0x01]),
+    InitCommand::new(0x19, 0, &[0x6E]),
+    InitCommand::new(0x16, 0, &[0x01]),
+    InitCommand::new(0x17, 0, &[0x41]),
+    InitCommand::new(0x14, 0, &[0x00]),
+    InitCommand::new(0x15, 0, &[0xfd]),
+    InitCommand::new(0x12, 0, &[0x00]),
+    InitCommand::new(0x13, 0, &[0xCF]),
+    InitCommand::new(0x10, 0, &[0x00]),
+    InitCommand::new(0x11, 0, &[0x98]),
+    InitCommand::new(0x0e, 0, &[0x00]),
+    InitCommand::new(0x0f, 0, &[0x89]),
+    InitCommand::new(0x0c, 0, &[0x00]),
+    InitCommand::new(0x0d, 0, &[0x79]),
+    InitCommand::new(0x0a, 0, &[0x00]),
+    InitCommand::new(0x0b, 0, &[0x67]),
+    InitCommand::new(0x08, 0, &[0x00]),
+    InitCommand::new(0x09, 0, &[0x55]),
+    InitCommand::new(0x06, 0, &[0x00]),
+    InitCommand::new(0x07, 0, &[0x3F]),
+    InitCommand::new(0x04, 0, &[0x00]),
+    InitCommand::new(0x05, 0, &[0x28]),
+    InitCommand::new(0x02, 0, &[0x00]),
+    InitCommand::new(0x03, 0, &[0x0E]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x11]),
+    InitCommand::new(0x60, 0, &[0x01]),
+    InitCommand::new(0x65, 0, &[0x03]),
+    InitCommand::new(0x66, 0, &[0x38]),
+    InitCommand::new(0x67, 0, &[0x04]),
+    InitCommand::new(0x68, 0, &[0x34]),
+    InitCommand::new(0x69, 0, &[0x03]),
+    InitCommand::new(0x61, 0, &[0x03]),
+    InitCommand::new(0x62, 0, &[0x38]),
+    InitCommand::new(0x63, 0, &[0x04]),
+    InitCommand::new(0x64, 0, &[0x34]),
+    InitCommand::new(0x0A, 0, &[0x11]),
+    InitCommand::new(0x0B, 0, &[0x20]),
+    InitCommand::new(0x0c, 0, &[0x20]),
+    InitCommand::new(0x55, 0, &[0x06]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x42]),
+    InitCommand::new(0x05, 0, &[0x3D]),
+    InitCommand::new(0x06, 0, &[0x03]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x1F, 0, &[0xDC]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x17]),
+    InitCommand::new(0x11, 0, &[0xAA]),
+    InitCommand::new(0x16, 0, &[0x12]),
+    InitCommand::new(0x0B, 0, &[0xC3]),
+    InitCommand::new(0x10, 0, &[0x0E]),
+    InitCommand::new(0x14, 0, &[0xAA]),
+    InitCommand::new(0x18, 0, &[0xA0]),
+    InitCommand::new(0x1A, 0, &[0x80]),
+    InitCommand::new(0x1F, 0, &[0x80]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x11]),
+    InitCommand::new(0x30, 0, &[0xEE]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x15, 0, &[0x0F]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x2D]),
+    InitCommand::new(0x01, 0, &[0x3E]),
+    InitCommand::new(0xff, 0, &[0x20, 0x10, 0x40]),
+    InitCommand::new(0x83, 0, &[0xC4]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x00, 0, &[0xCC]),
+    InitCommand::new(0x36, 0, &[0xA0]), // 1010 0000
+    InitCommand::new(0x2A, 0, &[0x2D]),
+    InitCommand::new(0x2B, 0, &[0x1e]),
+    InitCommand::new(0x2C, 0, &[0x26]),
+    InitCommand::new(0x2D, 0, &[0x2D]),
+    InitCommand::new(0x2E, 0, &[0x1e]),
+    InitCommand::new(0x1F, 0, &[0xE6]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0xA0]),
+    InitCommand::new(0x08, 0, &[0xE6]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x10, 0, &[0x0F]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x18]),
+    InitCommand::new(0x01, 0, &[0x01]),
+    InitCommand::new(0x00, 0, &[0x1E]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x43]),
+    InitCommand::new(0x03, 0, &[0x04]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x18]),
+    InitCommand::new(0x3A, 0, &[0x01]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x50]),
+    InitCommand::new(0x05, 0, &[0x08]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x50]),
+    InitCommand::new(0x00, 0, &[0xA6]),
+    InitCommand::new(0x01, 0, &[0xA6]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x50]),
+    InitCommand::new(0x08, 0, &[0x55]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x10]),
+    InitCommand::new(0x0B, 0, &[0x43]),
+    InitCommand::new(0x0C, 0, &[0x12]),
+    InitCommand::new(0x10, 0, &[0x01]),
+    InitCommand::new(0x11, 0, &[0x12]),
+    InitCommand::new(0x15, 0, &[0x00]),
+    InitCommand::new(0x16, 0, &[0x00]),
+    InitCommand::new(0x1A, 0, &[0x00]),
+    InitCommand::new(0x1B, 0, &[0x00]),
+    InitCommand::new(0x61, 0, &[0x00]),
+    InitCommand::new(0x62, 0, &[0x00]),
+    InitCommand::new(0x51, 0, &[0x11]),
+    InitCommand::new(0x55, 0, &[0x55]),
+    InitCommand::new(0x58, 0, &[0x00]),
+    InitCommand::new(0x5C, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x10]),
+    InitCommand::new(0x20, 0, &[0x81]),
+    InitCommand::new(0x21, 0, &[0x82]),
+    InitCommand::new(0x22, 0, &[0x72]),
+    InitCommand::new(0x30, 0, &[0x00]),
+    InitCommand::new(0x31, 0, &[0x00]),
+    InitCommand::new(0x32, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x10]),
+    InitCommand::new(0x44, 0, &[0x44]),
+    InitCommand::new(0x45, 0, &[0x55]),
+    InitCommand::new(0x46, 0, &[0x66]),
+    InitCommand::new(0x47, 0, &[0x77]),
+    InitCommand::new(0x49, 0, &[0x00]),
+    InitCommand::new(0x4A, 0, &[0x00]),
+    InitCommand::new(0x4B, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x17]),
+    InitCommand::new(0x37, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x15]),
+    InitCommand::new(0x04, 0, &[0x08]),
+    InitCommand::new(0x05, 0, &[0x04]),
+    InitCommand::new(0x06, 0, &[0x1C]),
+    InitCommand::new(0x07, 0, &[0x1A]),
+    InitCommand::new(0x08, 0, &[0x18]),
+    InitCommand::new(0x09, 0, &[0x16]),
+    InitCommand::new(0x24, 0, &[0x05]),
+    InitCommand::new(0x25, 0, &[0x09]),
+    InitCommand::new(0x26, 0, &[0x17]),
+    InitCommand::new(0x27, 0, &[0x19]),
+    InitCommand::new(0x28, 0, &[0x1B]),
+    InitCommand::new(0x29, 0, &[0x1D]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x16]),
+    InitCommand::new(0x04, 0, &[0x09]),
+    InitCommand::new(0x05, 0, &[0x05]),
+    InitCommand::new(0x06, 0, &[0x1D]),
+    InitCommand::new(0x07, 0, &[0x1B]),
+    InitCommand::new(0x08, 0, &[0x19]),
+    InitCommand::new(0x09, 0, &[0x17]),
+    InitCommand::new(0x24, 0, &[0x04]),
+    InitCommand::new(0x25, 0, &[0x08]),
+    InitCommand::new(0x26, 0, &[0x16]),
+    InitCommand::new(0x27, 0, &[0x18]),
+    InitCommand::new(0x28, 0, &[0x1A]),
+    InitCommand::new(0x29, 0, &[0x1C]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x18]),
+    InitCommand::new(0x1F, 0, &[0x02]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x11]),
+    InitCommand::new(0x15, 0, &[0x99]),
+    InitCommand::new(0x16, 0, &[0x99]),
+    InitCommand::new(0x1C, 0, &[0x88]),
+    InitCommand::new(0x1D, 0, &[0x88]),
+    InitCommand::new(0x1E, 0, &[0x88]),
+    InitCommand::new(0x13, 0, &[0xf0]),
+    InitCommand::new(0x14, 0, &[0x34]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x12, 0, &[0x89]),
+    InitCommand::new(0x06, 0, &[0x06]),
+    InitCommand::new(0x18, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x11]),
+    InitCommand::new(0x0A, 0, &[0x00]),
+    InitCommand::new(0x0B, 0, &[0xF0]),
+    InitCommand::new(0x0c, 0, &[0xF0]),
+    InitCommand::new(0x6A, 0, &[0x10]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x11]),
+    InitCommand::new(0x08, 0, &[0x70]),
+    InitCommand::new(0x09, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0x35, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x12]),
+    InitCommand::new(0x21, 0, &[0x70]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x2D]),
+    InitCommand::new(0x02, 0, &[0x00]),
+    InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]),
+    InitCommand::new(0x11, 120, &[0x00]),
 ];
@@ -0,0 +1,311 @@
+// Panel init sequences, either as a table of `InitCommand`s or as a compact
+// binary blob. Internally everything is kept as a blob, which is a list of
+// records: [ CMD, DELAY (ms), LEN, DATA (LEN bytes) ].
+
+use alloc::{borrow::Cow, vec::Vec};
+
+use super::{
+    config::{SPD2010_CMD_SET, SPD2010_CMD_SET_BYTE0, SPD2010_CMD_SET_BYTE1, SPD2010_CMD_SET_USER},
+    init_cmd::LCD_INIT_CMD,
+};
+
+pub const MAX_PAYLOAD_LEN: usize = 32;
+
+// The board's own sequence, turned into a blob at compile time so `default`
+// doesn't allocate. Checked by `vendor_sequence_is_valid`.
+static VENDOR_BLOB: [u8; blob_len(LCD_INIT_CMD)] = to_blob(LCD_INIT_CMD);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitCommand<'a> {
+    pub cmd: u8,
+    // Wait after sending, in ms
+    pub delay: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> InitCommand<'a> {
+    pub const fn new(cmd: u8, delay: u8, data: &'a [u8]) -> Self {
+        Self { cmd, delay, data }
+    }
+
+    /// Page selected by a `0xFF` command, if this is one.
+    pub fn page(&self) -> Option<u8> {
+        (self.cmd == SPD2010_CMD_SET).then(|| self.data.get(2).copied().unwrap_or(0))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitSequenceError {
+    PayloadTooLong { index: usize },
+    /// Page selects are `0xFF 0x20 0x10 <page>`
+    InvalidPageSelect { index: usize },
+    /// Later commands (COLMOD, DISPON, ...) need the user page selected
+    EndsOnVendorPage,
+    /// The blob ends part way through a record
+    Truncated { offset: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitSequence {
+    blob: Cow<'static, [u8]>,
+}
+
+impl InitSequence {
+    pub fn from_table(table: &[InitCommand]) -> Result<Self, InitSequenceError> {
+        let mut blob = Vec::new();
+        for (index, command) in table.iter().enumerate() {
+            // The length has to fit in a byte before `validate` gets to see it
+            if command.data.len() > MAX_PAYLOAD_LEN {
+                return Err(InitSequenceError::PayloadTooLong { index });
+            }
+            blob.extend_from_slice(&[command.cmd, command.delay, command.data.len() as u8]);
+            blob.extend_from_slice(command.data);
+        }
+        Self::from_blob(Cow::Owned(blob))
+    }
+
+    /// Blob in the record format above, e.g. from `include_bytes!` or flash.
+    pub fn from_blob(blob: impl Into<Cow<'static, [u8]>>) -> Result<Self, InitSequenceError> {
+        let sequence = Self { blob: blob.into() };
+        sequence.validate()?;
+        Ok(sequence)
+    }
+
+    pub fn as_blob(&self) -> &[u8] {
+        &self.blob
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = InitCommand<'_>> {
+        Records {
+            blob: &self.blob,
+            offset: 0,
+        }
+        .map_while(Result::ok)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blob.is_empty()
+    }
+
+    fn validate(&self) -> Result<(), InitSequenceError> {
+        let mut page = SPD2010_CMD_SET_USER;
+        let records = Records {
+            blob: &self.blob,
+            offset: 0,
+        };
+
+        for (index, command) in records.enumerate() {
+            let command = command?;
+            if command.data.len() > MAX_PAYLOAD_LEN {
+                return Err(InitSequenceError::PayloadTooLong { index });
+            }
+            if command.cmd == SPD2010_CMD_SET {
+                match command.data {
+                    [SPD2010_CMD_SET_BYTE0, SPD2010_CMD_SET_BYTE1, selected] => page = *selected,
+                    _ => return Err(InitSequenceError::InvalidPageSelect { index }),
+                }
+            }
+        }
+
+        if page != SPD2010_CMD_SET_USER {
+            return Err(InitSequenceError::EndsOnVendorPage);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for InitSequence {
+    /// The sequence this board ships with.
+    fn default() -> Self {
+        Self {
+            blob: Cow::Borrowed(&VENDOR_BLOB),
+        }
+    }
+}
+
+const fn blob_len(table: &[InitCommand]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < table.len() {
+        len += 3 + table[i].data.len();
+        i += 1;
+    }
+    len
+}
+
+const fn to_blob<const N: usize>(table: &[InitCommand]) -> [u8; N] {
+    let mut blob = [0; N];
+    let mut offset = 0;
+    let mut i = 0;
+    while i < table.len() {
+        let command = &table[i];
+        assert!(command.data.len() <= MAX_PAYLOAD_LEN);
+        blob[offset] = command.cmd;
+        blob[offset + 1] = command.delay;
+        blob[offset + 2] = command.data.len() as u8;
+        let mut j = 0;
+        while j < command.data.len() {
+            blob[offset + 3 + j] = command.data[j];
+            j += 1;
+        }
+        offset += 3 + command.data.len();
+        i += 1;
+    }
+    blob
+}
+
+struct Records<'a> {
+    blob: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<InitCommand<'a>, InitSequenceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let blob: &'a [u8] = self.blob;
+        let (header, rest) = match &blob[self.offset..] {
+            [] => return None,
+            [cmd, delay, len, rest @ ..] => ((*cmd, *delay, *len as usize), rest),
+            _ => return Some(Err(self.truncated())),
+        };
+
+        let (cmd, delay, len) = header;
+        let Some(data) = rest.get(..len) else {
+            return Some(Err(self.truncated()));
+        };
+
+        self.offset += 3 + len;
+        Some(Ok(InitCommand::new(cmd, delay, data)))
+    }
+}
+
+impl Records<'_> {
+    fn truncated(&mut self) -> InitSequenceError {
+        let offset = self.offset;
+        // Stop iterating
+        self.offset = self.blob.len();
+        InitSequenceError::Truncated { offset }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_USER: InitCommand = InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x00]);
+    const PAGE_10: InitCommand = InitCommand::new(0xFF, 0, &[0x20, 0x10, 0x10]);
+
+    #[test]
+    fn vendor_sequence_is_valid() {
+        let sequence = InitSequence::default();
+        assert_eq!(
+            InitSequence::from_blob(VENDOR_BLOB.as_slice()),
+            Ok(sequence.clone())
+        );
+        assert_eq!(InitSequence::from_table(LCD_INIT_CMD), Ok(sequence.clone()));
+        assert!(sequence.iter().eq(LCD_INIT_CMD.iter().copied()));
+        assert_eq!(sequence.len(), LCD_INIT_CMD.len());
+    }
+
+    #[test]
+    fn round_trips_through_blobs() {
+        let table = [
+            PAGE_10,
+            InitCommand::new(0x0C, 0, &[0x11]),
+            PAGE_USER,
+            InitCommand::new(0x11, 120, &[]),
+        ];
+        let sequence = InitSequence::from_table(&table).unwrap();
+        assert_eq!(
+            sequence.as_blob(),
+            [
+                0xFF, 0, 3, 0x20, 0x10, 0x10, //
+                0x0C, 0, 1, 0x11, //
+                0xFF, 0, 3, 0x20, 0x10, 0x00, //
+                0x11, 120, 0,
+            ]
+        );
+
+        let copy = InitSequence::from_blob(sequence.as_blob().to_vec()).unwrap();
+        assert_eq!(copy, sequence);
+        assert!(copy.iter().eq(table));
+        assert_eq!(copy.len(), 4);
+        assert_eq!(
+            copy.iter().map(|command| command.page()).nth(2),
+            Some(Some(0))
+        );
+
+        let empty = InitSequence::from_blob(Vec::new()).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.len(), 0);
+    }
+
+    #[test]
+    fn rejects_long_payloads() {
+        let long = [0x55; MAX_PAYLOAD_LEN + 1];
+        let table = [
+            InitCommand::new(0x0C, 0, &[0x11]),
+            InitCommand::new(0x2C, 0, &long),
+        ];
+        assert_eq!(
+            InitSequence::from_table(&table),
+            Err(InitSequenceError::PayloadTooLong { index: 1 })
+        );
+
+        // Still fits the length byte, so it's up to `validate`
+        let mut blob = vec![0x2C, 0, long.len() as u8];
+        blob.extend_from_slice(&long);
+        assert_eq!(
+            InitSequence::from_blob(blob),
+            Err(InitSequenceError::PayloadTooLong { index: 0 })
+        );
+
+        let longest = [0x55; MAX_PAYLOAD_LEN];
+        assert!(InitSequence::from_table(&[InitCommand::new(0x2C, 0, &longest)]).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_page_selects() {
+        for data in [
+            &[0x20, 0x10][..],
+            &[0x21, 0x10, 0x00],
+            &[0x20, 0x10, 0x00, 0x00],
+        ] {
+            let table = [
+                InitCommand::new(0x11, 0, &[]),
+                InitCommand::new(0xFF, 0, data),
+            ];
+            assert_eq!(
+                InitSequence::from_table(&table),
+                Err(InitSequenceError::InvalidPageSelect { index: 1 }),
+                "{data:x?}"
+            );
+        }
+
+        assert_eq!(
+            InitSequence::from_table(&[PAGE_10, InitCommand::new(0x0C, 0, &[0x11])]),
+            Err(InitSequenceError::EndsOnVendorPage)
+        );
+        assert!(InitSequence::from_table(&[PAGE_10, PAGE_USER]).is_ok());
+    }
+
+    #[test]
+    fn rejects_truncated_blobs() {
+        // In the header
+        assert_eq!(
+            InitSequence::from_blob(vec![0x11, 0]),
+            Err(InitSequenceError::Truncated { offset: 0 })
+        );
+        // In the second record's payload
+        assert_eq!(
+            InitSequence::from_blob(vec![0x11, 0, 0, 0x29, 0, 2, 0x01]),
+            Err(InitSequenceError::Truncated { offset: 3 })
+        );
+    }
+}
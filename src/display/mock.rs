@@ -0,0 +1,189 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::convert::Infallible;
+
+use embassy_time::Instant;
+use embedded_graphics::{prelude::Point, primitives::Rectangle};
+
+use super::{
+    config::opcode,
+    draw::Spd2010,
+    init_sequence::{InitCommand, InitSequence},
+    lcd_command,
+    pixel_format::PixelFormat,
+    transport::{QspiTransport, TearingEffect},
+};
+
+/// One transfer as the panel sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub opcode: u8,
+    pub cmd: u8,
+    pub data: Vec<u8>,
+}
+
+impl Transfer {
+    /// The single line header sent before the payload.
+    pub fn header(&self) -> [u8; 4] {
+        [self.opcode, 0, self.cmd, 0]
+    }
+
+    pub fn is_pixels(&self) -> bool {
+        self.opcode == opcode::WRITE_COLOR
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.opcode == opcode::READ_CMD
+    }
+}
+
+/// Transport that records every transfer instead of sending it, so the
+/// display logic can be checked off-device.
+#[derive(Debug, Default)]
+pub struct RecordingBus {
+    pub transfers: Vec<Transfer>,
+    // When each transfer went out, for checking the delays between them
+    pub times: Vec<Instant>,
+    // What reads of each register return, anything else reads as zeroes
+    pub registers: BTreeMap<u8, Vec<u8>>,
+}
+
+impl RecordingBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_register(&mut self, cmd: u8, value: &[u8]) {
+        self.registers.insert(cmd, value.to_vec());
+    }
+
+    pub fn clear(&mut self) {
+        self.transfers.clear();
+        self.times.clear();
+    }
+
+    fn push(&mut self, transfer: Transfer) {
+        self.transfers.push(transfer);
+        self.times.push(Instant::now());
+    }
+
+    /// Every byte that went over the wire, headers included.
+    pub fn byte_stream(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for transfer in &self.transfers {
+            bytes.extend_from_slice(&transfer.header());
+            bytes.extend_from_slice(&transfer.data);
+        }
+        bytes
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &Transfer> {
+        self.transfers
+            .iter()
+            .filter(|t| t.opcode == opcode::WRITE_CMD)
+    }
+
+    pub fn pixel_bytes(&self) -> usize {
+        self.transfers
+            .iter()
+            .filter(|t| t.is_pixels())
+            .map(|t| t.data.len())
+            .sum()
+    }
+
+    pub fn pixel_transfers(&self) -> usize {
+        self.transfers.iter().filter(|t| t.is_pixels()).count()
+    }
+
+    /// Windows set through CASET/RASET pairs, in the order they were sent.
+    pub fn windows(&self) -> Vec<Rectangle> {
+        let mut windows = Vec::new();
+        let mut columns = None;
+        for transfer in self.commands() {
+            match transfer.cmd {
+                lcd_command::CASET => columns = decode_range(&transfer.data),
+                lcd_command::RASET => {
+                    if let (Some((x1, x2)), Some((y1, y2))) = (columns, decode_range(&transfer.data))
+                    {
+                        windows.push(Rectangle::with_corners(
+                            Point::new(x1 as i32, y1 as i32),
+                            Point::new(x2 as i32, y2 as i32),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        windows
+    }
+}
+
+impl QspiTransport for RecordingBus {
+    type Error = Infallible;
+
+    fn write_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), Self::Error> {
+        self.push(Transfer {
+            opcode: opcode::WRITE_CMD,
+            cmd,
+            data: data.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn write_pixels(&mut self, cmd: u8, pixels: &[u8]) -> Result<(), Self::Error> {
+        self.push(Transfer {
+            opcode: opcode::WRITE_COLOR,
+            cmd,
+            data: pixels.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn read_command(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        buffer.fill(0);
+        if let Some(value) = self.registers.get(&cmd) {
+            let len = value.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&value[..len]);
+        }
+        self.push(Transfer {
+            opcode: opcode::READ_CMD,
+            cmd,
+            data: buffer.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+/// Tearing effect source that never blocks, counting how often it was waited on.
+#[derive(Debug, Default)]
+pub struct NoTearing {
+    pub waits: usize,
+}
+
+impl TearingEffect for NoTearing {
+    async fn wait_for_tear(&mut self) {
+        self.waits += 1;
+    }
+}
+
+/// Display on `bus` for tests, asleep as after reset. Its `init` only sends
+/// SLPOUT, without the vendor sequence and its delays.
+pub fn display_on<B: QspiTransport, C: PixelFormat>(bus: B) -> Spd2010<B, NoTearing, C> {
+    let sequence = [InitCommand::new(lcd_command::SLPOUT, 0, &[])];
+    Spd2010::new(bus, NoTearing::default())
+        .with_init_sequence(InitSequence::from_table(&sequence).unwrap())
+}
+
+/// `display_on` a new `RecordingBus`.
+pub fn display<C: PixelFormat>() -> Spd2010<RecordingBus, NoTearing, C> {
+    display_on(RecordingBus::new())
+}
+
+fn decode_range(data: &[u8]) -> Option<(u16, u16)> {
+    match data {
+        [s1, s0, e1, e0] => Some((
+            u16::from_be_bytes([*s1, *s0]),
+            u16::from_be_bytes([*e1, *e0]),
+        )),
+        _ => None,
+    }
+}
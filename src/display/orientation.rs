@@ -0,0 +1,132 @@
+use embedded_graphics::{prelude::Point, primitives::Rectangle};
+
+use super::config::{DISPLAY_X_MAX, DISPLAY_Y_MAX};
+
+pub mod madctl {
+    pub const MY: u8 = 0x80; // Row address order
+    pub const MX: u8 = 0x40; // Column address order
+    pub const MV: u8 = 0x20; // Row / column exchange
+    pub const BGR: u8 = 0x08;
+}
+
+/// Clockwise rotation of the UI relative to the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// Rotation, then optional mirroring of the rotated image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub mirror_x: bool,
+    pub mirror_y: bool,
+}
+
+impl Orientation {
+    pub const fn new(rotation: Rotation) -> Self {
+        Self {
+            rotation,
+            mirror_x: false,
+            mirror_y: false,
+        }
+    }
+
+    pub const fn with_mirror_x(mut self, mirror_x: bool) -> Self {
+        self.mirror_x = mirror_x;
+        self
+    }
+
+    pub const fn with_mirror_y(mut self, mirror_y: bool) -> Self {
+        self.mirror_y = mirror_y;
+        self
+    }
+
+    /// The whole mapping from UI coordinates to panel coordinates.
+    pub fn transform(&self) -> Transform {
+        let (swap, flip_x, flip_y) = match self.rotation {
+            Rotation::Deg0 => (false, false, false),
+            Rotation::Deg90 => (true, true, false),
+            Rotation::Deg180 => (false, true, true),
+            Rotation::Deg270 => (true, false, true),
+        };
+        Transform {
+            swap,
+            flip_x: flip_x ^ self.mirror_x,
+            flip_y: flip_y ^ self.mirror_y,
+        }
+    }
+
+    /// Splits the mapping into the MADCTL value and the part left for software.
+    ///
+    /// The SPD2010 only honours MX, so the column flip is the one thing the
+    /// panel can do for us. Row order and exchange stay in `draw_iter`.
+    pub fn split(&self) -> (u8, Transform) {
+        let transform = self.transform();
+        let madctl = if transform.flip_x { madctl::MX } else { 0 };
+        let software = Transform {
+            flip_x: false,
+            ..transform
+        };
+        (madctl, software)
+    }
+}
+
+/// Maps a point from UI space into framebuffer space. Applied in order:
+/// swap x/y, then flip each axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Transform {
+    pub swap: bool,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Transform {
+    pub fn is_identity(&self) -> bool {
+        !self.swap && !self.flip_x && !self.flip_y
+    }
+
+    // Only valid for points already on the screen
+    pub fn map(&self, x: u32, y: u32) -> (u32, u32) {
+        let (x, y) = if self.swap { (y, x) } else { (x, y) };
+        let x = if self.flip_x { DISPLAY_X_MAX - x } else { x };
+        let y = if self.flip_y { DISPLAY_Y_MAX - y } else { y };
+        (x, y)
+    }
+
+    pub fn map_point(&self, point: Point) -> Point {
+        let (x, y) = self.map(point.x as u32, point.y as u32);
+        Point::new(x as i32, y as i32)
+    }
+
+    /// The other way, from framebuffer space back to UI space. Works for
+    /// points off the screen too.
+    pub fn unmap_point(&self, point: Point) -> Point {
+        let (max_x, max_y) = (DISPLAY_X_MAX as i32, DISPLAY_Y_MAX as i32);
+        let x = if self.flip_x {
+            max_x - point.x
+        } else {
+            point.x
+        };
+        let y = if self.flip_y {
+            max_y - point.y
+        } else {
+            point.y
+        };
+        let (x, y) = if self.swap { (y, x) } else { (x, y) };
+        Point::new(x, y)
+    }
+
+    // Rectangles stay rectangles, so mapping two corners is enough
+    pub fn map_rect(&self, area: &Rectangle) -> Option<Rectangle> {
+        let bottom_right = area.bottom_right()?;
+        Some(Rectangle::with_corners(
+            self.map_point(area.top_left),
+            self.map_point(bottom_right),
+        ))
+    }
+}
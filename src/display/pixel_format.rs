@@ -0,0 +1,79 @@
+use embedded_graphics::pixelcolor::{
+    Rgb565, Rgb666, Rgb888, RgbColor,
+    raw::{RawData, RawU16},
+};
+
+use super::config::{DISPLAY_HEIGHT, DISPLAY_WIDTH, DMA_CHUNK_ROWS};
+
+/// A colour type the panel can be fed with, and how it is laid out in the
+/// framebuffer and on the wire.
+pub trait PixelFormat: RgbColor + From<Rgb888> + Into<Rgb888> {
+    const BYTES: usize;
+    // Value sent with COLMOD during init
+    const COLMOD: u8;
+
+    const BUFFER_SIZE: usize = DISPLAY_WIDTH as usize * DISPLAY_HEIGHT as usize * Self::BYTES;
+    const DMA_CHUNK_SIZE: usize = DMA_CHUNK_ROWS * DISPLAY_WIDTH as usize * Self::BYTES;
+
+    fn write_bytes(self, out: &mut [u8]);
+
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+impl PixelFormat for Rgb565 {
+    const BYTES: usize = 2;
+    const COLMOD: u8 = 0x55;
+
+    fn write_bytes(self, out: &mut [u8]) {
+        let raw = RawU16::from(self).into_inner();
+        out[..2].copy_from_slice(&raw.to_be_bytes());
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        RawU16::new(u16::from_be_bytes([bytes[0], bytes[1]])).into()
+    }
+}
+
+// 6 bits per channel, sent MSB-aligned in 3 bytes
+impl PixelFormat for Rgb666 {
+    const BYTES: usize = 3;
+    const COLMOD: u8 = 0x66;
+
+    fn write_bytes(self, out: &mut [u8]) {
+        out[0] = self.r() << 2;
+        out[1] = self.g() << 2;
+        out[2] = self.b() << 2;
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Rgb666::new(bytes[0] >> 2, bytes[1] >> 2, bytes[2] >> 2)
+    }
+}
+
+impl PixelFormat for Rgb888 {
+    const BYTES: usize = 3;
+    const COLMOD: u8 = 0x77;
+
+    fn write_bytes(self, out: &mut [u8]) {
+        out[0] = self.r();
+        out[1] = self.g();
+        out[2] = self.b();
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Rgb888::new(bytes[0], bytes[1], bytes[2])
+    }
+}
+
+/// `foreground` over `background`, `alpha` being the foreground's coverage.
+pub fn blend(background: Rgb888, foreground: Rgb888, alpha: u8) -> Rgb888 {
+    let mix = |b: u8, f: u8| {
+        let (b, f, a) = (b as u32, f as u32, alpha as u32);
+        ((f * a + b * (255 - a) + 127) / 255) as u8
+    };
+    Rgb888::new(
+        mix(background.r(), foreground.r()),
+        mix(background.g(), foreground.g()),
+        mix(background.b(), foreground.b()),
+    )
+}
@@ -0,0 +1,19 @@
+use embassy_time::Duration;
+
+/// Power states of the panel, from most to least power hungry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    /// Normal full colour output
+    Active,
+    /// 8 colour mode, for always-on watchfaces
+    Idle,
+    /// Panel keeps running but shows nothing, frame memory can still be written
+    DisplayOff,
+    /// DC/DC, oscillator and scanning stopped, frame memory is kept
+    Sleep,
+}
+
+// Time the panel needs after SLPOUT before it takes SLPIN (and vice versa)
+pub const SLEEP_TOGGLE_DELAY: Duration = Duration::from_millis(120);
+// Time after SLPIN before any other command
+pub const SLEEP_IN_DELAY: Duration = Duration::from_millis(5);
@@ -0,0 +1,239 @@
+// Geometry for the circular panel. Angles here are watch angles: degrees
+// clockwise from 12 o'clock.
+
+use embedded_graphics::{
+    prelude::{Angle, Point, Size},
+    primitives::{Arc, Circle, Rectangle, Sector},
+};
+
+use super::config::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+pub const DIAMETER: u32 = DISPLAY_WIDTH;
+pub const RADIUS: u32 = DIAMETER / 2;
+pub const CENTER: Point = Point::new(DISPLAY_WIDTH as i32 / 2, DISPLAY_HEIGHT as i32 / 2);
+
+// Everything is done with doubled coordinates so pixel centres are integers
+const W: i32 = DISPLAY_WIDTH as i32;
+
+/// Whether the centre of a pixel lies inside the visible circle.
+pub fn is_visible(point: Point) -> bool {
+    let dx = 2 * point.x + 1 - W;
+    let dy = 2 * point.y + 1 - W;
+    dx * dx + dy * dy <= W * W
+}
+
+/// First and last visible x on a row.
+pub fn row_span(y: i32) -> Option<(i32, i32)> {
+    let dy = 2 * y + 1 - W;
+    let max = W * W - dy * dy;
+    if max < 0 {
+        return None;
+    }
+
+    let dx = isqrt(max);
+    let x1 = (W - 1 - dx + 1).div_euclid(2);
+    let x2 = (W - 1 + dx).div_euclid(2);
+    Some((x1, x2))
+}
+
+/// Shrinks an area to the part that can actually be seen.
+pub fn clip_rect(area: &Rectangle) -> Option<Rectangle> {
+    let area = area.intersection(&Rectangle::new(Point::zero(), Size::new_equal(DIAMETER)));
+    let bottom_right = area.bottom_right()?;
+
+    let mut min = Point::new(i32::MAX, i32::MAX);
+    let mut max = Point::new(i32::MIN, i32::MIN);
+    for y in area.rows() {
+        let Some((x1, x2)) = row_span(y) else {
+            continue;
+        };
+        let x1 = x1.max(area.top_left.x);
+        let x2 = x2.min(bottom_right.x);
+        if x1 <= x2 {
+            min = min.component_min(Point::new(x1, y));
+            max = max.component_max(Point::new(x2, y));
+        }
+    }
+
+    (min.x <= max.x).then(|| Rectangle::with_corners(min, max))
+}
+
+/// Widest rectangle over rows `top..top + height` that is entirely visible.
+pub fn band(top: i32, height: u32) -> Option<Rectangle> {
+    let bottom = top + height.max(1) as i32 - 1;
+    let (top_x1, top_x2) = row_span(top)?;
+    let (bottom_x1, bottom_x2) = row_span(bottom)?;
+    let (x1, x2) = (top_x1.max(bottom_x1), top_x2.min(bottom_x2));
+    (x1 <= x2).then(|| Rectangle::with_corners(Point::new(x1, top), Point::new(x2, bottom)))
+}
+
+/// Largest axis aligned square that fits inside the circle.
+pub fn safe_rect() -> Rectangle {
+    let side = (DIAMETER as f32 / core::f32::consts::SQRT_2) as u32;
+    Rectangle::with_center(CENTER, Size::new_equal(side))
+}
+
+/// Point at a watch angle and distance from the centre.
+pub fn polar(angle_deg: f32, radius: f32) -> Point {
+    let radians = angle_deg.to_radians();
+    Point::new(
+        CENTER.x + libm::roundf(radius * libm::sinf(radians)) as i32,
+        CENTER.y - libm::roundf(radius * libm::cosf(radians)) as i32,
+    )
+}
+
+/// Watch angle (0.0 - 360.0) and distance from the centre of a point.
+pub fn to_polar(point: Point) -> (f32, f32) {
+    let dx = (point.x - CENTER.x) as f32;
+    let dy = (point.y - CENTER.y) as f32;
+    let angle = libm::atan2f(dx, -dy).to_degrees();
+    let angle = if angle < 0.0 { angle + 360.0 } else { angle };
+    (angle, libm::sqrtf(dx * dx + dy * dy))
+}
+
+// embedded-graphics measures from 3 o'clock
+fn eg_angle(angle_deg: f32) -> Angle {
+    Angle::from_degrees(angle_deg - 90.0)
+}
+
+pub fn circle(diameter: u32) -> Circle {
+    Circle::with_center(CENTER, diameter)
+}
+
+pub fn arc(diameter: u32, start_deg: f32, sweep_deg: f32) -> Arc {
+    Arc::with_center(
+        CENTER,
+        diameter,
+        eg_angle(start_deg),
+        Angle::from_degrees(sweep_deg),
+    )
+}
+
+pub fn sector(diameter: u32, start_deg: f32, sweep_deg: f32) -> Sector {
+    Sector::with_center(
+        CENTER,
+        diameter,
+        eg_angle(start_deg),
+        Angle::from_degrees(sweep_deg),
+    )
+}
+
+/// Circle whose (centre aligned) stroke, drawn `thickness` wide, covers
+/// radii `outer_radius - thickness` to `outer_radius`.
+pub fn ring(outer_radius: u32, thickness: u32) -> Circle {
+    let thickness = thickness.min(outer_radius);
+    circle(2 * outer_radius - thickness)
+}
+
+fn isqrt(n: i32) -> i32 {
+    let mut root = libm::sqrtf(n as f32) as i32;
+    while root * root > n {
+        root -= 1;
+    }
+    while (root + 1) * (root + 1) <= n {
+        root += 1;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::primitives::PointsIter;
+
+    use super::*;
+
+    // Bounding box of the visible pixels in `area`, the slow way
+    fn visible_bounds(area: &Rectangle) -> Option<Rectangle> {
+        let visible = || area.points().filter(|&point| is_visible(point));
+        let min = visible().reduce(Point::component_min)?;
+        let max = visible().reduce(Point::component_max)?;
+        Some(Rectangle::with_corners(min, max))
+    }
+
+    #[test]
+    fn row_spans_follow_the_circle() {
+        // Widest across the middle
+        assert_eq!(row_span(CENTER.y - 1), Some((0, W - 1)));
+        assert_eq!(row_span(CENTER.y), Some((0, W - 1)));
+        // Narrowest at the edges, the same top and bottom
+        assert_eq!(row_span(0), Some((192, 219)));
+        assert_eq!(row_span(W - 1), row_span(0));
+        assert_eq!(row_span(-1), None);
+        assert_eq!(row_span(W), None);
+
+        for y in 0..W {
+            let (x1, x2) = row_span(y).unwrap();
+            assert_eq!(x1 + x2, W - 1, "row {y} isn't centred");
+            assert!(is_visible(Point::new(x1, y)) && is_visible(Point::new(x2, y)));
+            assert!(!is_visible(Point::new(x1 - 1, y)) && !is_visible(Point::new(x2 + 1, y)));
+        }
+    }
+
+    #[test]
+    fn clip_rect_keeps_the_visible_part() {
+        let screen = Rectangle::new(Point::zero(), Size::new_equal(DIAMETER));
+        assert_eq!(clip_rect(&screen), Some(screen));
+        assert_eq!(
+            clip_rect(&Rectangle::new(Point::zero(), Size::new(40, 40))),
+            None
+        );
+        assert_eq!(
+            clip_rect(&Rectangle::new(Point::new(-20, 500), Size::new(40, 40))),
+            None
+        );
+
+        for area in [
+            Rectangle::new(Point::new(150, 0), Size::new(100, 10)),
+            Rectangle::new(Point::new(0, 150), Size::new(30, 100)),
+            Rectangle::new(Point::new(300, 300), Size::new(200, 200)),
+            Rectangle::new(Point::new(-50, 200), Size::new(100, 1)),
+        ] {
+            assert_eq!(clip_rect(&area), visible_bounds(&area), "{area:?}");
+        }
+    }
+
+    #[test]
+    fn bands_are_fully_visible() {
+        let band_rect = band(CENTER.y - 10, 20).unwrap();
+        // Rows the same distance above and below the centre limit it equally
+        assert_eq!(band_rect.top_left.x, row_span(CENTER.y - 10).unwrap().0);
+        assert_eq!(band_rect.size.height, 20);
+        assert!(band_rect.points().all(is_visible));
+
+        let top = band(0, 30).unwrap();
+        assert_eq!(top.top_left.x, row_span(0).unwrap().0);
+        assert!(top.points().all(is_visible));
+        assert_eq!(band(0, 0), band(0, 1));
+        assert_eq!(band(-5, 10), None);
+        assert_eq!(band(W - 5, 10), None);
+    }
+
+    #[test]
+    fn safe_rect_fits_inside_the_circle() {
+        let rect = safe_rect();
+        assert_eq!(rect.center(), CENTER);
+        assert_eq!(rect.size, Size::new_equal(291));
+        assert!(rect.points().all(is_visible));
+    }
+
+    #[test]
+    fn polar_points_round_trip() {
+        assert_eq!(polar(0.0, 100.0), CENTER - Point::new(0, 100));
+        assert_eq!(polar(90.0, 100.0), CENTER + Point::new(100, 0));
+        assert_eq!(polar(180.0, 100.0), CENTER + Point::new(0, 100));
+        assert_eq!(polar(270.0, 100.0), CENTER - Point::new(100, 0));
+
+        assert_eq!(to_polar(CENTER - Point::new(0, 50)), (0.0, 50.0));
+        assert_eq!(to_polar(CENTER + Point::new(50, 0)), (90.0, 50.0));
+        // Just left of 12 o'clock is nearly a full turn, not negative
+        let (angle, _) = to_polar(CENTER + Point::new(-1, -100));
+        assert!((359.0..360.0).contains(&angle));
+
+        for degrees in (0..360).step_by(15) {
+            let (angle, radius) = to_polar(polar(degrees as f32, 150.0));
+            let error = (angle - degrees as f32 + 180.0).rem_euclid(360.0) - 180.0;
+            assert!(error.abs() < 0.5, "{degrees}: {angle}");
+            assert!((radius - 150.0).abs() < 1.0, "{degrees}: {radius}");
+        }
+    }
+}
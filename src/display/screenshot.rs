@@ -0,0 +1,424 @@
+// Encodes an area of the screen as BMP or QOI into any `embedded_io::Write`.
+// Pixels come from a closure so the same encoders work for the framebuffer,
+// GRAM readback or anything else.
+
+use alloc::vec::Vec;
+
+use embedded_graphics::{
+    pixelcolor::{Rgb888, RgbColor},
+    prelude::{Dimensions, Point, Size},
+    primitives::Rectangle,
+};
+use embedded_io::Write;
+
+use super::{
+    config::DISPLAY_WIDTH,
+    dirty,
+    draw::{Spd2010, set_draw_pos},
+    error::Error,
+    lcd_command,
+    pixel_format::PixelFormat,
+    transport::{QspiTransport, TearingEffect},
+};
+
+// Flush the encoder's scratch buffer once it gets this big
+const WRITE_CHUNK: usize = 512;
+
+pub fn write_bmp<W, F>(writer: &mut W, area: &Rectangle, pixel: F) -> Result<(), W::Error>
+where
+    W: Write,
+    F: Fn(Point) -> Rgb888,
+{
+    let (width, height) = (area.size.width, area.size.height);
+    // Rows are padded to 4 bytes
+    let row_len = (width * 3).next_multiple_of(4);
+    let image_size = row_len * height;
+    let header_size = 14 + 40;
+
+    let mut header = Vec::with_capacity(header_size as usize);
+    // File header
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&(header_size + image_size).to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&header_size.to_le_bytes());
+    // BITMAPINFOHEADER
+    header.extend_from_slice(&40u32.to_le_bytes());
+    header.extend_from_slice(&(width as i32).to_le_bytes());
+    header.extend_from_slice(&(height as i32).to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // Planes
+    header.extend_from_slice(&24u16.to_le_bytes()); // Bits per pixel
+    header.extend_from_slice(&0u32.to_le_bytes()); // No compression
+    header.extend_from_slice(&image_size.to_le_bytes());
+    header.extend_from_slice(&2835u32.to_le_bytes()); // 72 DPI
+    header.extend_from_slice(&2835u32.to_le_bytes());
+    header.extend_from_slice(&[0; 8]); // Palette
+    writer.write_all(&header)?;
+
+    // Bottom row first, BGR
+    let mut row = Vec::with_capacity(row_len as usize);
+    for y in area.rows().rev() {
+        row.clear();
+        for x in area.columns() {
+            let color = pixel(Point::new(x, y));
+            row.extend_from_slice(&[color.b(), color.g(), color.r()]);
+        }
+        row.resize(row_len as usize, 0);
+        writer.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+const QOI_OP_INDEX: u8 = 0x00;
+const QOI_OP_DIFF: u8 = 0x40;
+const QOI_OP_LUMA: u8 = 0x80;
+const QOI_OP_RUN: u8 = 0xC0;
+const QOI_OP_RGB: u8 = 0xFE;
+
+fn qoi_hash(color: Rgb888) -> usize {
+    (color.r() as usize * 3 + color.g() as usize * 5 + color.b() as usize * 7 + 255 * 11) % 64
+}
+
+pub fn write_qoi<W, F>(writer: &mut W, area: &Rectangle, pixel: F) -> Result<(), W::Error>
+where
+    W: Write,
+    F: Fn(Point) -> Rgb888,
+{
+    let mut out = Vec::with_capacity(WRITE_CHUNK + 16);
+    out.extend_from_slice(b"qoif");
+    out.extend_from_slice(&area.size.width.to_be_bytes());
+    out.extend_from_slice(&area.size.height.to_be_bytes());
+    out.extend_from_slice(&[3, 0]); // RGB, sRGB
+
+    // Starts out empty like the decoder's, opaque black isn't in it yet
+    let mut index = [None; 64];
+    let mut previous = Rgb888::BLACK;
+    let mut run = 0u8;
+
+    for y in area.rows() {
+        for x in area.columns() {
+            let color = pixel(Point::new(x, y));
+
+            if color == previous {
+                run += 1;
+                if run == 62 {
+                    out.push(QOI_OP_RUN | (run - 1));
+                    run = 0;
+                }
+            } else {
+                if run > 0 {
+                    out.push(QOI_OP_RUN | (run - 1));
+                    run = 0;
+                }
+
+                let hash = qoi_hash(color);
+                if index[hash] == Some(color) {
+                    out.push(QOI_OP_INDEX | hash as u8);
+                } else {
+                    index[hash] = Some(color);
+
+                    let dr = color.r().wrapping_sub(previous.r()) as i8;
+                    let dg = color.g().wrapping_sub(previous.g()) as i8;
+                    let db = color.b().wrapping_sub(previous.b()) as i8;
+                    let dr_dg = dr.wrapping_sub(dg);
+                    let db_dg = db.wrapping_sub(dg);
+
+                    if (-2..=1).contains(&dr) && (-2..=1).contains(&dg) && (-2..=1).contains(&db) {
+                        out.push(
+                            QOI_OP_DIFF
+                                | ((dr + 2) as u8) << 4
+                                | ((dg + 2) as u8) << 2
+                                | (db + 2) as u8,
+                        );
+                    } else if (-32..=31).contains(&dg)
+                        && (-8..=7).contains(&dr_dg)
+                        && (-8..=7).contains(&db_dg)
+                    {
+                        out.push(QOI_OP_LUMA | (dg + 32) as u8);
+                        out.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                    } else {
+                        out.extend_from_slice(&[QOI_OP_RGB, color.r(), color.g(), color.b()]);
+                    }
+                }
+                previous = color;
+            }
+
+            if out.len() >= WRITE_CHUNK {
+                writer.write_all(&out)?;
+                out.clear();
+            }
+        }
+    }
+
+    if run > 0 {
+        out.push(QOI_OP_RUN | (run - 1));
+    }
+    out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+    writer.write_all(&out)
+}
+
+impl<B, T, C> Spd2010<B, T, C>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    /// BMP of `area` of the framebuffer, in drawing coordinates.
+    pub fn save_bmp<W: Write>(&self, writer: &mut W, area: &Rectangle) -> Result<(), W::Error> {
+        let area = area.intersection(&self.bounding_box());
+        write_bmp(writer, &area, |point| self.screenshot_pixel(point))
+    }
+
+    /// Same as `save_bmp` but QOI, which is a lot smaller over a slow link.
+    pub fn save_qoi<W: Write>(&self, writer: &mut W, area: &Rectangle) -> Result<(), W::Error> {
+        let area = area.intersection(&self.bounding_box());
+        write_qoi(writer, &area, |point| self.screenshot_pixel(point))
+    }
+
+    fn screenshot_pixel(&self, point: Point) -> Rgb888 {
+        self.pixel(point).map(Into::into).unwrap_or(Rgb888::BLACK)
+    }
+
+    /// Reads `area` of the panel's frame memory (RAMRD / RAMRDC) into
+    /// `buffer`. `area` is in framebuffer coordinates and gets 4-aligned
+    /// like a flush would; returns the area actually read. Assumes the panel
+    /// reads back in the format set with COLMOD. Fails with
+    /// `Error::BufferTooSmall` if the aligned area doesn't fit in `buffer`.
+    pub fn read_gram(
+        &mut self,
+        area: &Rectangle,
+        buffer: &mut [u8],
+    ) -> Result<Option<Rectangle>, Error<B::Error>> {
+        let Some(area) = dirty::align(*area) else {
+            return Ok(None);
+        };
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(None);
+        };
+        let len = (area.size.width * area.size.height) as usize * C::BYTES;
+        let buffer = buffer.get_mut(..len).ok_or(Error::BufferTooSmall)?;
+
+        let qspi = self.transport_mut();
+        set_draw_pos(
+            qspi,
+            area.top_left.x as u16,
+            area.top_left.y as u16,
+            bottom_right.x as u16,
+            bottom_right.y as u16,
+        )?;
+
+        // Reads have to fit in the DMA receive buffer, same as writes
+        let mut cmd = lcd_command::RAMRD;
+        for chunk in buffer.chunks_mut(C::DMA_CHUNK_SIZE) {
+            qspi.read_command(cmd, chunk)?;
+            cmd = lcd_command::RAMRDC;
+        }
+
+        Ok(Some(area))
+    }
+
+    /// Reads `area` back from the panel row by row and checks it against the
+    /// framebuffer, as the colour pipeline sent it. Only meaningful once
+    /// `area` has been flushed.
+    pub fn verify_gram(&mut self, area: &Rectangle) -> Result<bool, Error<B::Error>> {
+        let Some(area) = dirty::align(*area) else {
+            return Ok(true);
+        };
+
+        let stride = DISPLAY_WIDTH as usize * C::BYTES;
+        let row_len = area.size.width as usize * C::BYTES;
+        let mut row = alloc::vec![0u8; row_len];
+        let mut expected = alloc::vec![0u8; row_len];
+
+        for y in area.rows() {
+            let row_area = Rectangle::new(
+                Point::new(area.top_left.x, y),
+                Size::new(area.size.width, 1),
+            );
+            self.read_gram(&row_area, &mut row)?;
+
+            let start = y as usize * stride + area.top_left.x as usize * C::BYTES;
+            expected.copy_from_slice(&self.framebuffer[start..start + row_len]);
+            if let Some(pipeline) = self.color_pipeline() {
+                pipeline.apply::<C>(&mut expected);
+            }
+            if row != expected {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{
+        Drawable,
+        pixelcolor::Rgb565,
+        prelude::{DrawTarget, Primitive},
+        primitives::{Circle, PointsIter, PrimitiveStyle},
+    };
+
+    use super::*;
+    use crate::display::{
+        color::{ColorMode, ColorPipeline},
+        image::{BlitOptions, Image},
+        mock::display,
+    };
+
+    const PIXELS: [[Rgb888; 3]; 2] = [
+        [Rgb888::RED, Rgb888::GREEN, Rgb888::BLUE],
+        [Rgb888::WHITE, Rgb888::BLACK, Rgb888::new(10, 20, 30)],
+    ];
+
+    fn sample(point: Point) -> Rgb888 {
+        PIXELS[point.y as usize][point.x as usize]
+    }
+
+    // Encodes into a fixed buffer, returning what was written
+    fn encode<F>(write: F) -> Vec<u8>
+    where
+        F: FnOnce(&mut &mut [u8]) -> Result<(), embedded_io::SliceWriteError>,
+    {
+        let mut buffer = alloc::vec![0u8; 64 * 1024];
+        let mut writer = &mut buffer[..];
+        write(&mut writer).unwrap();
+        let remaining = writer.len();
+        let written = buffer.len() - remaining;
+        buffer.truncate(written);
+        buffer
+    }
+
+    #[test]
+    fn bmp_matches_golden() {
+        let area = Rectangle::new(Point::zero(), Size::new(3, 2));
+        let bmp = encode(|writer| write_bmp(writer, &area, sample));
+
+        #[rustfmt::skip]
+        let golden: &[u8] = &[
+            b'B', b'M', 78, 0, 0, 0, 0, 0, 0, 0, 54, 0, 0, 0,
+            40, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1, 0, 24, 0,
+            0, 0, 0, 0, 24, 0, 0, 0, 0x13, 0x0B, 0, 0, 0x13, 0x0B, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            // Bottom row first, BGR, padded to 12 bytes
+            0xFF, 0xFF, 0xFF, 0, 0, 0, 30, 20, 10, 0, 0, 0,
+            0, 0, 0xFF, 0, 0xFF, 0, 0xFF, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(bmp, golden);
+    }
+
+    #[test]
+    fn qoi_matches_golden() {
+        let area = Rectangle::new(Point::zero(), Size::new(3, 2));
+        let qoi = encode(|writer| write_qoi(writer, &area, sample));
+
+        #[rustfmt::skip]
+        let golden: &[u8] = &[
+            b'q', b'o', b'i', b'f', 0, 0, 0, 3, 0, 0, 0, 2, 3, 0,
+            // Each primary is a small wrapping step from the one before
+            0x5A, 0x76, 0x6D, 0x56,
+            // Black is a step too, it isn't in the index yet
+            0x7F,
+            QOI_OP_RGB, 10, 20, 30,
+            0, 0, 0, 0, 0, 0, 0, 1,
+        ];
+        assert_eq!(qoi, golden);
+    }
+
+    #[test]
+    fn qoi_uses_runs_and_index() {
+        let a = Rgb888::new(100, 50, 25);
+        let c = Rgb888::new(200, 10, 10);
+        let row = [a, a, a, c, a];
+        let area = Rectangle::new(Point::zero(), Size::new(5, 1));
+        let qoi = encode(|writer| write_qoi(writer, &area, |p| row[p.x as usize]));
+
+        #[rustfmt::skip]
+        let golden: &[u8] = &[
+            b'q', b'o', b'i', b'f', 0, 0, 0, 5, 0, 0, 0, 1, 3, 0,
+            QOI_OP_RGB, 100, 50, 25,
+            QOI_OP_RUN | 1,
+            QOI_OP_RGB, 200, 10, 10,
+            QOI_OP_INDEX | 10,
+            0, 0, 0, 0, 0, 0, 0, 1,
+        ];
+        assert_eq!(qoi, golden);
+    }
+
+    #[test]
+    fn screenshots_round_trip_through_image() {
+        let mut source = display::<Rgb888>();
+        let Ok(()) = source.clear(Rgb888::new(20, 40, 60));
+        let Ok(()) = Circle::new(Point::new(110, 95), 30)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(250, 128, 3)))
+            .draw(&mut source);
+        for x in 100..140 {
+            let Ok(()) = source.fill_solid(
+                &Rectangle::new(Point::new(x, 120), Size::new(1, 10)),
+                Rgb888::new(x as u8, 255 - x as u8, 7),
+            );
+        }
+
+        let area = Rectangle::new(Point::new(100, 90), Size::new(40, 40));
+        let files = [
+            encode(|writer| source.save_bmp(writer, &area)),
+            encode(|writer| source.save_qoi(writer, &area)),
+        ];
+        for file in &files {
+            let image = Image::new(&file[..]).unwrap();
+            let mut copy = display::<Rgb888>();
+            copy.draw_image(&image, area.top_left, BlitOptions::default())
+                .unwrap();
+            for point in area.points() {
+                assert_eq!(copy.pixel(point), source.pixel(point), "{point:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn read_gram_rejects_short_buffers() {
+        let mut display = display::<Rgb565>();
+        // Aligned out to 4 pixels, 8 bytes
+        let area = Rectangle::new(Point::new(5, 7), Size::new(3, 1));
+        let mut buffer = [0u8; 7];
+        assert_eq!(
+            display.read_gram(&area, &mut buffer),
+            Err(Error::BufferTooSmall)
+        );
+        assert!(display.transport().transfers.is_empty());
+
+        let mut buffer = [0u8; 8];
+        let read = display.read_gram(&area, &mut buffer).unwrap();
+        assert_eq!(
+            read,
+            Some(Rectangle::new(Point::new(4, 7), Size::new(4, 1)))
+        );
+    }
+
+    #[test]
+    fn verify_gram_expects_pipeline_colours() {
+        let mut display = display::<Rgb888>();
+        let area = Rectangle::new(Point::new(40, 50), Size::new(8, 1));
+        let color = Rgb888::new(200, 180, 160);
+        let Ok(()) = display.fill_solid(&area, color);
+
+        let raw: Vec<u8> = [color.r(), color.g(), color.b()].repeat(8);
+        display
+            .transport_mut()
+            .set_register(lcd_command::RAMRD, &raw);
+        assert_eq!(display.verify_gram(&area), Ok(true));
+
+        let pipeline = ColorPipeline::new().with_mode(ColorMode::Night(200));
+        let sent = pipeline.transform(color);
+        assert_ne!(sent, color);
+        display.set_color_pipeline(pipeline);
+        assert_eq!(display.verify_gram(&area), Ok(false));
+
+        let mapped: Vec<u8> = [sent.r(), sent.g(), sent.b()].repeat(8);
+        display
+            .transport_mut()
+            .set_register(lcd_command::RAMRD, &mapped);
+        assert_eq!(display.verify_gram(&area), Ok(true));
+    }
+}
@@ -0,0 +1,160 @@
+use embedded_graphics::{
+    prelude::{Point, Size},
+    primitives::Rectangle,
+};
+
+use super::config::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// Rows of the panel that scroll, with fixed bands above and below
+/// (VSCRDEF). Rows are in panel space, i.e. after rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrollArea {
+    pub top_fixed: u16,
+    pub height: u16,
+    pub bottom_fixed: u16,
+}
+
+impl ScrollArea {
+    /// Everything between the two fixed bands scrolls.
+    pub fn new(top_fixed: u16, bottom_fixed: u16) -> Self {
+        let top_fixed = top_fixed.min(DISPLAY_HEIGHT as u16);
+        let bottom_fixed = bottom_fixed.min(DISPLAY_HEIGHT as u16 - top_fixed);
+        Self {
+            top_fixed,
+            height: DISPLAY_HEIGHT as u16 - top_fixed - bottom_fixed,
+            bottom_fixed,
+        }
+    }
+
+    pub fn contains_row(&self, row: u16) -> bool {
+        (self.top_fixed..self.top_fixed + self.height).contains(&row)
+    }
+
+    /// The scrolling rows, full width.
+    pub fn rows(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(0, self.top_fixed as i32),
+            Size::new(DISPLAY_WIDTH, self.height as u32),
+        )
+    }
+
+    pub(crate) fn vscrdef_data(&self) -> [u8; 6] {
+        let [t1, t0] = self.top_fixed.to_be_bytes();
+        let [h1, h0] = self.height.to_be_bytes();
+        let [b1, b0] = self.bottom_fixed.to_be_bytes();
+        [t1, t0, h1, h0, b1, b0]
+    }
+}
+
+/// Active hardware scrolling state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scroll {
+    pub area: ScrollArea,
+    pub offset: u16,
+}
+
+impl Scroll {
+    /// Frame memory row shown on screen row `row`.
+    pub fn memory_row(&self, row: u16) -> u16 {
+        if !self.area.contains_row(row) || self.area.height == 0 {
+            return row;
+        }
+        let top = self.area.top_fixed;
+        top + (row - top + self.offset) % self.area.height
+    }
+
+    /// Value for VSCSAD, the memory row shown at the top of the scroll area.
+    pub(crate) fn start_address(&self) -> u16 {
+        self.area.top_fixed + self.offset
+    }
+}
+
+/// Rows that stay lit in partial mode (PTLAR), inclusive. Rows are in panel
+/// space like `ScrollArea`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialArea {
+    start_row: u16,
+    end_row: u16,
+}
+
+impl PartialArea {
+    /// None if the rows are the wrong way round or start below the panel,
+    /// `end_row` is cut short at the last row.
+    pub fn new(start_row: u16, end_row: u16) -> Option<Self> {
+        let last_row = DISPLAY_HEIGHT as u16 - 1;
+        (start_row <= end_row && start_row <= last_row).then(|| Self {
+            start_row,
+            end_row: end_row.min(last_row),
+        })
+    }
+
+    pub fn start_row(&self) -> u16 {
+        self.start_row
+    }
+
+    pub fn end_row(&self) -> u16 {
+        self.end_row
+    }
+
+    /// The part of `area` that's lit.
+    pub fn clip(&self, area: &Rectangle) -> Option<Rectangle> {
+        let rows = Rectangle::new(
+            Point::new(0, self.start_row as i32),
+            Size::new(DISPLAY_WIDTH, (self.end_row - self.start_row) as u32 + 1),
+        );
+        let area = area.intersection(&rows);
+        (!area.is_zero_sized()).then_some(area)
+    }
+
+    pub(crate) fn ptlar_data(&self) -> [u8; 4] {
+        let [s1, s0] = self.start_row.to_be_bytes();
+        let [e1, e0] = self.end_row.to_be_bytes();
+        [s1, s0, e1, e0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_area_rows_are_checked() {
+        let area = PartialArea::new(100, 200).unwrap();
+        assert_eq!((area.start_row(), area.end_row()), (100, 200));
+        assert_eq!(area.ptlar_data(), [0, 100, 0, 200]);
+
+        assert!(PartialArea::new(7, 7).is_some());
+        assert_eq!(PartialArea::new(201, 200), None);
+        assert_eq!(PartialArea::new(DISPLAY_HEIGHT as u16, 1000), None);
+        let bottom = PartialArea::new(400, 1000).unwrap();
+        assert_eq!(bottom.end_row(), DISPLAY_HEIGHT as u16 - 1);
+    }
+
+    #[test]
+    fn partial_area_clips_to_its_rows() {
+        let area = PartialArea::new(100, 199).unwrap();
+        let band = Rectangle::new(Point::new(8, 50), Size::new(16, 100));
+        assert_eq!(
+            area.clip(&band),
+            Some(Rectangle::new(Point::new(8, 100), Size::new(16, 50)))
+        );
+        let above = Rectangle::new(Point::new(8, 0), Size::new(16, 100));
+        assert_eq!(area.clip(&above), None);
+    }
+
+    #[test]
+    fn scroll_maps_rows_into_memory() {
+        let scroll = Scroll {
+            area: ScrollArea::new(10, 2),
+            offset: 5,
+        };
+        assert_eq!(scroll.area.height, DISPLAY_HEIGHT as u16 - 12);
+        assert_eq!(scroll.memory_row(3), 3);
+        assert_eq!(scroll.memory_row(10), 15);
+        assert_eq!(scroll.memory_row(DISPLAY_HEIGHT as u16 - 3), 14);
+        assert_eq!(
+            scroll.memory_row(DISPLAY_HEIGHT as u16 - 1),
+            DISPLAY_HEIGHT as u16 - 1
+        );
+    }
+}
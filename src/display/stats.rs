@@ -0,0 +1,163 @@
+use alloc::{format, string::String};
+
+use embassy_time::{Duration, Instant};
+use heapless::Deque;
+
+// Frames the rolling FPS is averaged over
+const FPS_WINDOW: usize = 16;
+
+/// What one flush cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// Waiting for the TE edge
+    pub tear_wait: Duration,
+    /// Sending pixels, from the first window to the last chunk
+    pub transfer: Duration,
+    pub total: Duration,
+    pub bytes: usize,
+    /// Pixel transfers issued (RAMWR / RAMWRC)
+    pub chunks: u32,
+}
+
+/// Collected by `Spd2010` once enabled with `set_stats_enabled`.
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    last: FrameTiming,
+    frames: u32,
+    total_bytes: u64,
+    max_total: Duration,
+    // When the most recent flushes started
+    starts: Deque<Instant, FPS_WINDOW>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record(&mut self, started: Instant, timing: FrameTiming) {
+        self.last = timing;
+        self.frames += 1;
+        self.total_bytes += timing.bytes as u64;
+        self.max_total = self.max_total.max(timing.total);
+
+        if self.starts.is_full() {
+            self.starts.pop_front();
+        }
+        self.starts.push_back(started).ok();
+    }
+
+    pub fn last(&self) -> FrameTiming {
+        self.last
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Slowest flush so far.
+    pub fn max_total(&self) -> Duration {
+        self.max_total
+    }
+
+    /// Flushes per second over the last few frames.
+    pub fn fps(&self) -> f32 {
+        let (Some(first), Some(last)) = (self.starts.front(), self.starts.back()) else {
+            return 0.0;
+        };
+        let elapsed = (*last - *first).as_micros();
+        if elapsed == 0 {
+            return 0.0;
+        }
+        (self.starts.len() - 1) as f32 * 1_000_000.0 / elapsed as f32
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// One line summary, e.g. for the overlay or a log.
+    pub fn summary(&self) -> String {
+        let last = &self.last;
+        format!(
+            "{:.1} fps {}ms (te {}ms) {}kB/{}",
+            self.fps(),
+            last.total.as_millis(),
+            last.tear_wait.as_millis(),
+            last.bytes / 1024,
+            last.chunks,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(total_ms: u64, tear_wait_ms: u64, bytes: usize, chunks: u32) -> FrameTiming {
+        FrameTiming {
+            tear_wait: Duration::from_millis(tear_wait_ms),
+            transfer: Duration::from_millis(total_ms - tear_wait_ms),
+            total: Duration::from_millis(total_ms),
+            bytes,
+            chunks,
+        }
+    }
+
+    #[test]
+    fn empty_stats_read_as_zero() {
+        let stats = FrameStats::new();
+        assert_eq!(stats.fps(), 0.0);
+        assert_eq!(stats.frames(), 0);
+        assert_eq!(stats.last(), FrameTiming::default());
+        assert_eq!(stats.summary(), "0.0 fps 0ms (te 0ms) 0kB/0");
+
+        // One frame has no interval to go by
+        let mut stats = FrameStats::new();
+        stats.record(Instant::from_millis(500), timing(10, 2, 100, 1));
+        assert_eq!(stats.fps(), 0.0);
+    }
+
+    #[test]
+    fn records_totals_and_the_slowest_frame() {
+        let mut stats = FrameStats::new();
+        for (i, total) in [12, 30, 8, 15, 12].into_iter().enumerate() {
+            let started = Instant::from_millis(20 * i as u64);
+            stats.record(started, timing(total, 3, 300 * 1024, 7));
+        }
+
+        assert_eq!(stats.frames(), 5);
+        assert_eq!(stats.total_bytes(), 5 * 300 * 1024);
+        assert_eq!(stats.max_total(), Duration::from_millis(30));
+        assert_eq!(stats.last(), timing(12, 3, 300 * 1024, 7));
+        // Four 20ms intervals
+        assert_eq!(stats.fps(), 50.0);
+        assert_eq!(stats.summary(), "50.0 fps 12ms (te 3ms) 300kB/7");
+
+        stats.reset();
+        assert_eq!(stats.frames(), 0);
+        assert_eq!(stats.fps(), 0.0);
+    }
+
+    #[test]
+    fn fps_only_covers_the_window() {
+        let mut stats = FrameStats::new();
+        // A long pause before the first full window, then 10ms frames
+        stats.record(Instant::from_millis(0), timing(5, 0, 0, 1));
+        for i in 0..FPS_WINDOW as u64 {
+            stats.record(Instant::from_millis(1000 + 10 * i), timing(5, 0, 0, 1));
+        }
+        assert_eq!(stats.frames(), FPS_WINDOW as u32 + 1);
+        assert_eq!(stats.fps(), 100.0);
+
+        // Keeps rolling once full
+        for i in 0..FPS_WINDOW as u64 {
+            stats.record(Instant::from_millis(2000 + 20 * i), timing(5, 0, 0, 1));
+        }
+        assert_eq!(stats.fps(), 50.0);
+    }
+}
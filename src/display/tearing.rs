@@ -0,0 +1,144 @@
+use embassy_time::{Duration, Instant, Timer};
+
+use super::transport::TearingEffect;
+
+/// What the TE output reports, the parameter of TEON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TearingMode {
+    /// Pulse on vertical blanking only
+    VBlank = 0x00,
+    /// Pulse on both vertical and horizontal blanking
+    VAndHBlank = 0x01,
+}
+
+/// Keeps rendering locked to a whole number of panel refreshes and counts
+/// the refreshes that were missed. Each frame starts on a TE pulse, the
+/// timer only decides which one.
+pub struct FramePacer {
+    refresh_period: Duration,
+    period: Duration,
+    next: Instant,
+    frames: u32,
+    missed: u32,
+}
+
+impl FramePacer {
+    /// `refresh_period` is the panel's, as measured by
+    /// `Spd2010::measure_refresh_period`. `divider` refreshes make one frame.
+    pub fn new(refresh_period: Duration, divider: u32) -> Self {
+        let period = refresh_period * divider.max(1);
+        Self {
+            refresh_period,
+            period,
+            next: Instant::now() + period,
+            frames: 0,
+            missed: 0,
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Frames started so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Frames skipped because rendering ran over, in total.
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    /// Waits for the TE pulse of the next frame slot, see
+    /// `Spd2010::wait_frame`. Returns how many slots were missed since the
+    /// last call.
+    pub async fn wait(&mut self, tear_input: &mut impl TearingEffect) -> u32 {
+        let now = Instant::now();
+        let mut missed = 0;
+        if now > self.next {
+            let late = (now - self.next).as_ticks() / self.period.as_ticks().max(1);
+            missed = late as u32 + 1;
+            self.next += self.period * missed;
+        }
+
+        // Wake half a refresh early and start on the pulse, the timer alone
+        // would drift against the panel's clock
+        Timer::at(self.next - self.refresh_period / 2).await;
+        tear_input.wait_for_tear().await;
+        self.next = Instant::now() + self.period;
+        self.frames += 1;
+        self.missed += missed;
+
+        missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use super::*;
+    use crate::display::mock::NoTearing;
+
+    const REFRESH: Duration = Duration::from_millis(20);
+
+    // TE pulses every `REFRESH`, `phase` after `start`
+    struct Vsync {
+        start: Instant,
+        phase: Duration,
+    }
+
+    impl TearingEffect for Vsync {
+        async fn wait_for_tear(&mut self) {
+            let first = self.start + self.phase;
+            let since = Instant::now().saturating_duration_since(first);
+            let pulses = (since.as_ticks() / REFRESH.as_ticks()) as u32 + 1;
+            Timer::at(first + REFRESH * pulses).await;
+        }
+    }
+
+    #[test]
+    fn counts_missed_frames() {
+        let mut tear_input = NoTearing::default();
+        let mut pacer = FramePacer::new(REFRESH, 1);
+        assert_eq!(block_on(pacer.wait(&mut tear_input)), 0);
+        assert_eq!(block_on(pacer.wait(&mut tear_input)), 0);
+
+        // Rendering ran over by two and a half slots
+        block_on(Timer::after(REFRESH * 5 / 2));
+        assert_eq!(block_on(pacer.wait(&mut tear_input)), 2);
+        assert_eq!(block_on(pacer.wait(&mut tear_input)), 0);
+
+        assert_eq!(pacer.frames(), 4);
+        assert_eq!(pacer.missed(), 2);
+        // Every frame started on a pulse
+        assert_eq!(tear_input.waits, 4);
+    }
+
+    #[test]
+    fn frames_start_on_the_pulse() {
+        let start = Instant::now();
+        let phase = Duration::from_millis(7);
+        let mut tear_input = Vsync { start, phase };
+        let mut pacer = FramePacer::new(REFRESH, 2);
+        assert_eq!(pacer.period(), REFRESH * 2);
+
+        let mut last = None;
+        for _ in 0..3 {
+            assert_eq!(block_on(pacer.wait(&mut tear_input)), 0);
+            let now = Instant::now();
+            let offset = (now - start).as_ticks() % REFRESH.as_ticks();
+            let offset = Duration::from_ticks(offset);
+            assert!(
+                offset >= phase && offset < phase + REFRESH / 4,
+                "{offset:?}"
+            );
+            if let Some(last) = last {
+                let between = now - last;
+                assert!(between > REFRESH * 3 / 2 && between < REFRESH * 5 / 2);
+            }
+            last = Some(now);
+        }
+    }
+}
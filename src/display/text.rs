@@ -0,0 +1,478 @@
+// Text on top of u8g2-fonts: the fonts that look right on this panel (see
+// fonts.txt) plus the wrapping, alignment and ellipsis the renderer doesn't do.
+
+use alloc::{borrow::Cow, format, vec::Vec};
+use core::iter;
+
+use embedded_graphics::{
+    prelude::{DrawTarget, DrawTargetExt, PixelColor, Point, Size},
+    primitives::Rectangle,
+};
+use u8g2_fonts::{
+    Error as FontError, FontRenderer, LookupError, fonts,
+    types::{FontColor, VerticalPosition},
+};
+
+pub use u8g2_fonts::types::HorizontalAlignment;
+
+use super::round;
+
+// The `_tr` fonts only have ASCII
+const ELLIPSIS: &str = "...";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    /// logisoso 92px, digits and ':' only
+    ClockDigits,
+    /// Lucida Sans 14px
+    Small,
+    /// Lucida Sans 18px
+    Medium,
+    /// ProFont 22px, monospaced
+    Mono,
+    /// Unifont symbols, for icons
+    Icons,
+}
+
+impl Font {
+    pub fn renderer(self) -> FontRenderer {
+        match self {
+            Font::ClockDigits => FontRenderer::new::<fonts::u8g2_font_logisoso92_tn>(),
+            Font::Small => FontRenderer::new::<fonts::u8g2_font_luRS14_tr>(),
+            Font::Medium => FontRenderer::new::<fonts::u8g2_font_luRS18_tr>(),
+            Font::Mono => FontRenderer::new::<fonts::u8g2_font_profont22_tr>(),
+            Font::Icons => FontRenderer::new::<fonts::u8g2_font_unifont_t_symbols>(),
+        }
+    }
+
+    pub fn line_height(self) -> u32 {
+        self.renderer().get_default_line_height()
+    }
+}
+
+/// Where a block of lines goes inside its rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerticalAlignment {
+    Top,
+    Center,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextStyle<C> {
+    pub font: Font,
+    pub color: C,
+    pub background: Option<C>,
+    pub horizontal: HorizontalAlignment,
+    pub vertical: VerticalAlignment,
+    /// Added to the font's line height
+    pub line_spacing: i32,
+    /// Break lines at spaces to fit the width
+    pub wrap: bool,
+    /// End lines that don't fit with "..." instead of clipping them
+    pub ellipsis: bool,
+}
+
+impl<C: PixelColor> TextStyle<C> {
+    pub fn new(font: Font, color: C) -> Self {
+        Self {
+            font,
+            color,
+            background: None,
+            horizontal: HorizontalAlignment::Left,
+            vertical: VerticalAlignment::Top,
+            line_spacing: 0,
+            wrap: true,
+            ellipsis: true,
+        }
+    }
+
+    pub fn with_background(mut self, background: C) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn with_alignment(
+        mut self,
+        horizontal: HorizontalAlignment,
+        vertical: VerticalAlignment,
+    ) -> Self {
+        self.horizontal = horizontal;
+        self.vertical = vertical;
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: i32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_ellipsis(mut self, ellipsis: bool) -> Self {
+        self.ellipsis = ellipsis;
+        self
+    }
+
+    // Distance from one line's top to the next
+    fn line_pitch(&self) -> i32 {
+        self.font.line_height() as i32 + self.line_spacing
+    }
+
+    fn font_color(&self) -> FontColor<C> {
+        match self.background {
+            Some(bg) => FontColor::WithBackground { fg: self.color, bg },
+            None => FontColor::Transparent(self.color),
+        }
+    }
+}
+
+/// Advance width of a single line.
+pub fn text_width(font: Font, line: &str) -> Result<u32, LookupError> {
+    let dimensions =
+        font.renderer()
+            .get_rendered_dimensions(line, Point::zero(), VerticalPosition::Baseline)?;
+    Ok(dimensions.advance.x.max(0) as u32)
+}
+
+/// Size of `text` as drawn without wrapping, one line per `\n`.
+pub fn measure(font: Font, text: &str) -> Result<Size, LookupError> {
+    let mut width = 0;
+    let mut lines = 0;
+    for line in text.split('\n') {
+        width = width.max(text_width(font, line)?);
+        lines += 1;
+    }
+    Ok(Size::new(width, lines * font.line_height()))
+}
+
+/// `line` cut short with "..." so it fits in `max_width`.
+pub fn ellipsize(font: Font, line: &str, max_width: u32) -> Result<Cow<'_, str>, LookupError> {
+    if text_width(font, line)? <= max_width {
+        return Ok(Cow::Borrowed(line));
+    }
+
+    let available = max_width.saturating_sub(text_width(font, ELLIPSIS)?);
+    let mut end = line.len();
+    while end > 0 {
+        end = line[..end].char_indices().next_back().map_or(0, |(i, _)| i);
+        if text_width(font, line[..end].trim_end())? <= available {
+            break;
+        }
+    }
+
+    let kept = line[..end].trim_end();
+    Ok(Cow::Owned(format!("{kept}{ELLIPSIS}")))
+}
+
+/// Splits `text` into lines no wider than `max_width`, breaking at spaces
+/// and `\n`. A single word that is too wide gets a line of its own.
+pub fn wrap(font: Font, text: &str, max_width: u32) -> Result<Vec<&str>, LookupError> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut start = 0;
+        // End of the last word that fitted
+        let mut end = 0;
+        let breaks = paragraph.match_indices(' ').map(|(i, _)| i);
+        for i in breaks.chain(iter::once(paragraph.len())) {
+            if end > start && text_width(font, &paragraph[start..i])? > max_width {
+                lines.push(&paragraph[start..end]);
+                start = end + 1;
+            }
+            end = i;
+        }
+        lines.push(&paragraph[start..]);
+    }
+    Ok(lines)
+}
+
+/// The lines `draw_text` would draw into a `size` box.
+pub fn layout<'a, C: PixelColor>(
+    text: &'a str,
+    size: Size,
+    style: &TextStyle<C>,
+) -> Result<Vec<Cow<'a, str>>, LookupError> {
+    let lines = if style.wrap {
+        wrap(style.font, text, size.width)?
+    } else {
+        text.split('\n').collect()
+    };
+
+    let pitch = style.line_pitch().max(1);
+    let max_lines = ((size.height as i32 + style.line_spacing) / pitch).max(1) as usize;
+    let cut = lines.len() > max_lines;
+
+    let mut laid_out = Vec::with_capacity(lines.len().min(max_lines));
+    for (i, line) in lines.into_iter().take(max_lines).enumerate() {
+        let line = match style.ellipsis {
+            // Show there was more
+            true if cut && i + 1 == max_lines => {
+                let line = format!("{line}{ELLIPSIS}");
+                Cow::Owned(ellipsize(style.font, &line, size.width)?.into_owned())
+            }
+            true => ellipsize(style.font, line, size.width)?,
+            false => Cow::Borrowed(line),
+        };
+        laid_out.push(line);
+    }
+
+    Ok(laid_out)
+}
+
+/// Draws `text` inside `bounds`, anything outside it is clipped.
+pub fn draw_text<D>(
+    display: &mut D,
+    text: &str,
+    bounds: &Rectangle,
+    style: &TextStyle<D::Color>,
+) -> Result<(), FontError<D::Error>>
+where
+    D: DrawTarget,
+{
+    let lines = layout(text, bounds.size, style).map_err(lookup_error)?;
+
+    let pitch = style.line_pitch();
+    let block_height = lines.len() as i32 * pitch - style.line_spacing;
+    let top = match style.vertical {
+        VerticalAlignment::Top => bounds.top_left.y,
+        VerticalAlignment::Center => bounds.center().y - block_height / 2,
+        VerticalAlignment::Bottom => bounds.top_left.y + bounds.size.height as i32 - block_height,
+    };
+    let x = match style.horizontal {
+        HorizontalAlignment::Left => bounds.top_left.x,
+        HorizontalAlignment::Center => bounds.center().x,
+        HorizontalAlignment::Right => bounds.top_left.x + bounds.size.width as i32,
+    };
+
+    let renderer = style.font.renderer();
+    let mut clipped = display.clipped(bounds);
+    for (i, line) in lines.iter().enumerate() {
+        let position = Point::new(x, top + i as i32 * pitch);
+        renderer.render_aligned(
+            line.as_ref(),
+            position,
+            VerticalPosition::Top,
+            style.horizontal,
+            style.font_color(),
+            &mut clipped,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Draws `text` centred on the round screen around row `center_y`, one line
+/// per `\n`. Each line is cut to the width of the circle where it lands;
+/// the style's alignment and wrapping are ignored.
+pub fn draw_text_round<D>(
+    display: &mut D,
+    text: &str,
+    center_y: i32,
+    style: &TextStyle<D::Color>,
+) -> Result<(), FontError<D::Error>>
+where
+    D: DrawTarget,
+{
+    let renderer = style.font.renderer();
+    let line_height = style.font.line_height();
+    let pitch = style.line_pitch();
+
+    let lines = text.split('\n').count() as i32;
+    let top = center_y - (lines * pitch - style.line_spacing) / 2;
+
+    for (i, line) in text.split('\n').enumerate() {
+        let y = top + i as i32 * pitch;
+        let Some(band) = round::band(y, line_height) else {
+            continue;
+        };
+        let line = match style.ellipsis {
+            true => ellipsize(style.font, line, band.size.width).map_err(lookup_error)?,
+            false => Cow::Borrowed(line),
+        };
+
+        renderer.render_aligned(
+            line.as_ref(),
+            Point::new(round::CENTER.x, y),
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+            style.font_color(),
+            display,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn lookup_error<E>(error: LookupError) -> FontError<E> {
+    match error {
+        LookupError::GlyphNotFound(c) => FontError::GlyphNotFound(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use embedded_graphics::{Pixel, pixelcolor::BinaryColor, prelude::OriginDimensions};
+
+    use super::*;
+
+    const FONT: Font = Font::Mono;
+
+    // Remembers where pixels were drawn, including off the panel
+    #[derive(Default)]
+    struct Canvas(Vec<Point>);
+
+    impl OriginDimensions for Canvas {
+        fn size(&self) -> Size {
+            Size::new_equal(round::DIAMETER)
+        }
+    }
+
+    impl DrawTarget for Canvas {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.0
+                .extend(pixels.into_iter().map(|Pixel(point, _)| point));
+            Ok(())
+        }
+    }
+
+    fn width(line: &str) -> u32 {
+        text_width(FONT, line).unwrap()
+    }
+
+    #[test]
+    fn wraps_at_spaces_and_newlines() {
+        let max = width("one two");
+        assert_eq!(
+            wrap(FONT, "one two three", max).unwrap(),
+            ["one two", "three"]
+        );
+        assert_eq!(wrap(FONT, "one\ntwo six", max).unwrap(), ["one", "two six"]);
+        // Exactly as wide as the limit still fits
+        assert_eq!(wrap(FONT, "one two", max).unwrap(), ["one two"]);
+    }
+
+    #[test]
+    fn too_wide_word_gets_its_own_line() {
+        let max = width("a b");
+        assert_eq!(
+            wrap(FONT, "a enormous b", max).unwrap(),
+            ["a", "enormous", "b"]
+        );
+        assert_eq!(wrap(FONT, "enormous", 1).unwrap(), ["enormous"]);
+    }
+
+    #[test]
+    fn ellipsize_keeps_what_fits() {
+        let line = "hello world";
+        assert!(matches!(
+            ellipsize(FONT, line, width(line)).unwrap(),
+            Cow::Borrowed("hello world")
+        ));
+
+        let max = width("hello...");
+        assert_eq!(ellipsize(FONT, line, max).unwrap(), "hello...");
+        // The space before the cut is dropped
+        let max = width("hello w...") - 1;
+        assert_eq!(ellipsize(FONT, line, max).unwrap(), "hello...");
+        // Nothing fits next to the ellipsis
+        assert_eq!(ellipsize(FONT, line, 1).unwrap(), "...");
+    }
+
+    #[test]
+    fn layout_stops_at_max_lines() {
+        let two_lines = Size::new(width("three") * 2, 2 * FONT.line_height());
+        let style = TextStyle::new(FONT, BinaryColor::On)
+            .with_wrap(false)
+            .with_ellipsis(false);
+        assert_eq!(
+            layout("one\ntwo\nthree", two_lines, &style).unwrap(),
+            ["one", "two"]
+        );
+
+        let ellipsis = style.with_ellipsis(true);
+        assert_eq!(
+            layout("one\ntwo\nthree", two_lines, &ellipsis).unwrap(),
+            ["one", "two..."]
+        );
+
+        // Line spacing takes room between lines but not after the last one
+        let spaced = style.with_line_spacing(4);
+        assert_eq!(layout("one\ntwo", two_lines, &spaced).unwrap(), ["one"]);
+        let taller = Size::new(two_lines.width, two_lines.height + 4);
+        assert_eq!(layout("one\ntwo", taller, &spaced).unwrap(), ["one", "two"]);
+
+        // A box shorter than a line still gets one
+        assert_eq!(
+            layout("one\ntwo", Size::new(100, 1), &style).unwrap(),
+            ["one"]
+        );
+    }
+
+    #[test]
+    fn layout_wraps_and_ellipsizes_each_line() {
+        let size = Size::new(width("one two"), 10 * FONT.line_height());
+        let style = TextStyle::new(FONT, BinaryColor::On).with_ellipsis(false);
+        assert_eq!(
+            layout("one two three", size, &style).unwrap(),
+            ["one two", "three"]
+        );
+
+        let style = style.with_wrap(false).with_ellipsis(true);
+        let lines = layout("one two three", size, &style).unwrap();
+        assert_eq!(lines, ["one..."]);
+    }
+
+    #[test]
+    fn round_text_is_cut_to_the_circle() {
+        let line = "0123456789".repeat(4);
+        let style = TextStyle::new(FONT, BinaryColor::On);
+        let center_y = 40;
+
+        let mut canvas = Canvas::default();
+        draw_text_round(&mut canvas, &line, center_y, &style).unwrap();
+        let top = center_y - FONT.line_height() as i32 / 2;
+        let band = round::band(top, FONT.line_height()).unwrap();
+        assert!(!canvas.0.is_empty());
+        assert!(canvas.0.iter().all(|point| round::is_visible(*point)));
+        assert!(
+            canvas
+                .0
+                .iter()
+                .all(|point| band.columns().contains(&point.x))
+        );
+
+        // Without the ellipsis the line runs off the screen
+        let mut canvas = Canvas::default();
+        let style = style.with_ellipsis(false);
+        draw_text_round(&mut canvas, &line, center_y, &style).unwrap();
+        assert!(canvas.0.iter().any(|point| !round::is_visible(*point)));
+    }
+
+    #[test]
+    fn round_text_skips_lines_off_the_circle() {
+        let style = TextStyle::new(FONT, BinaryColor::On);
+        let mut canvas = Canvas::default();
+        // The last of three lines starts at row 20, the two above it are off
+        // the screen
+        let pitch = FONT.line_height() as i32;
+        let center_y = 20 + 3 * pitch / 2;
+        draw_text_round(&mut canvas, "gone\ngone\nhere", center_y, &style).unwrap();
+        assert!(!canvas.0.is_empty());
+        assert!(canvas.0.iter().all(|point| point.y >= 0));
+
+        let mut canvas = Canvas::default();
+        draw_text_round(&mut canvas, "gone", -100, &style).unwrap();
+        assert!(canvas.0.is_empty());
+    }
+}
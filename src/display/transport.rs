@@ -0,0 +1,96 @@
+use embassy_time::{Duration, Timer};
+#[cfg(target_arch = "xtensa")]
+use esp_hal::{
+    DriverMode,
+    gpio::Input,
+    spi::{
+        self,
+        master::{Address, Command, DataMode, SpiDmaBus},
+    },
+};
+
+#[cfg(target_arch = "xtensa")]
+use super::config::opcode;
+
+// Dummy clocks between the address and the data on reads
+pub const READ_DUMMY_CYCLES: u8 = 0;
+
+/// The kinds of transfer the SPD2010 understands over QSPI.
+///
+/// All are framed as `[ OPCODE, 0, CMD, 0 ]` followed by the payload; commands
+/// and reads move their payload on a single line, pixels use all four.
+#[allow(async_fn_in_trait)]
+pub trait QspiTransport {
+    type Error;
+
+    fn write_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), Self::Error>;
+
+    fn write_pixels(&mut self, cmd: u8, pixels: &[u8]) -> Result<(), Self::Error>;
+
+    fn read_command(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Waits `duration` before the next transfer, e.g. after SLPOUT. A
+    /// transport that queues its transfers has to queue the wait too.
+    async fn delay(&mut self, duration: Duration) {
+        Timer::after(duration).await
+    }
+}
+
+/// Something that can wait for the panel's tearing effect (TE) signal.
+#[allow(async_fn_in_trait)]
+pub trait TearingEffect {
+    async fn wait_for_tear(&mut self);
+}
+
+#[cfg(target_arch = "xtensa")]
+impl<'a, Dm> QspiTransport for SpiDmaBus<'a, Dm>
+where
+    Dm: DriverMode,
+{
+    type Error = spi::Error;
+
+    fn write_command(&mut self, cmd: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let address_value = (cmd as u32) << 8;
+        self.half_duplex_write(
+            DataMode::Single,
+            Command::_8Bit(opcode::WRITE_CMD as u16, DataMode::Single),
+            Address::_24Bit(address_value, DataMode::Single),
+            0,
+            data,
+        )
+    }
+
+    fn write_pixels(&mut self, cmd: u8, pixels: &[u8]) -> Result<(), Self::Error> {
+        let address_value = (cmd as u32) << 8;
+        self.half_duplex_write(
+            DataMode::Quad,
+            Command::_8Bit(opcode::WRITE_COLOR as u16, DataMode::Single),
+            Address::_24Bit(address_value, DataMode::Single),
+            0,
+            pixels,
+        )
+    }
+
+    fn read_command(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let address_value = (cmd as u32) << 8;
+        self.half_duplex_read(
+            DataMode::Single,
+            Command::_8Bit(opcode::READ_CMD as u16, DataMode::Single),
+            Address::_24Bit(address_value, DataMode::Single),
+            READ_DUMMY_CYCLES,
+            buffer,
+        )
+    }
+}
+
+// No TE line wired up, don't wait at all
+impl TearingEffect for () {
+    async fn wait_for_tear(&mut self) {}
+}
+
+#[cfg(target_arch = "xtensa")]
+impl<'a> TearingEffect for Input<'a> {
+    async fn wait_for_tear(&mut self) {
+        self.wait_for_falling_edge().await;
+    }
+}
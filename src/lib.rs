@@ -1,11 +1,39 @@
-#![no_std]
-#![feature(allocator_api, new_zeroed_alloc, type_alias_impl_trait)]
+#![cfg_attr(not(test), no_std)]
+#![feature(new_zeroed_alloc)]
 
 extern crate alloc;
 
+// Logging goes to the serial console on the device. Host builds (the tests)
+// have no console, so it's compiled out there.
+#[cfg(target_arch = "xtensa")]
+pub(crate) use esp_println::{print, println};
+
+#[cfg(not(target_arch = "xtensa"))]
+macro_rules! host_print {
+    ($($arg:tt)*) => {{
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+#[cfg(not(target_arch = "xtensa"))]
+macro_rules! host_println {
+    () => {};
+    ($($arg:tt)*) => {{
+        let _ = format_args!($($arg)*);
+    }};
+}
+
+#[cfg(not(target_arch = "xtensa"))]
+pub(crate) use {host_print as print, host_println as println};
+
 pub mod display;
 // pub mod exio;
+#[cfg(target_arch = "xtensa")]
 pub mod gyroscope;
+#[cfg(target_arch = "xtensa")]
 pub mod interface;
+#[cfg(target_arch = "xtensa")]
 pub mod power_btn;
+#[cfg(target_arch = "xtensa")]
 pub mod speaker;
+pub mod touch;
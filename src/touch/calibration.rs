@@ -0,0 +1,393 @@
+// Touch coordinates don't quite line up with the panel: the digitiser can be
+// offset, scaled or slightly rotated. `Calibration` is an affine map from raw
+// touch coordinates to panel coordinates, fitted from touches on known targets.
+// Rotation is handled afterwards by `Touch`, so one calibration holds for every
+// display orientation.
+
+use embedded_graphics::prelude::Point;
+#[cfg(target_arch = "xtensa")]
+use embedded_graphics::{
+    Drawable,
+    prelude::{DrawTarget, Primitive},
+    primitives::{Circle, Line, PrimitiveStyle},
+};
+
+#[cfg(target_arch = "xtensa")]
+use super::{
+    controller::{Touch, TouchError},
+    event::TouchPhase,
+};
+#[cfg(target_arch = "xtensa")]
+use crate::display::{
+    draw::Spd2010,
+    error::Error,
+    orientation::Orientation,
+    pixel_format::PixelFormat,
+    round,
+    transport::{QspiTransport, TearingEffect},
+};
+
+/// Bytes in `Calibration::to_bytes`.
+pub const CALIBRATION_LEN: usize = 2 + 6 * 4;
+
+const MAGIC: u8 = b'T';
+const VERSION: u8 = 1;
+
+// Where the calibration targets go, as watch angle and distance from the
+// centre. Far enough out to see scaling, far enough in to be comfortable.
+#[cfg(target_arch = "xtensa")]
+const TARGETS: [(f32, f32); 5] = [
+    (0.0, 0.0),
+    (45.0, 140.0),
+    (135.0, 140.0),
+    (225.0, 140.0),
+    (315.0, 140.0),
+];
+#[cfg(target_arch = "xtensa")]
+const TARGET_SIZE: u32 = 24;
+
+#[cfg(target_arch = "xtensa")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationError<E> {
+    Touch(TouchError),
+    Display(Error<E>),
+    /// The touches were all on one line, nothing to fit
+    Degenerate,
+}
+
+/// `x' = a x + b y + c`, `y' = d x + e y + f`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    coefficients: [f32; 6],
+}
+
+impl Calibration {
+    /// Leaves touch coordinates as they are.
+    pub const IDENTITY: Self = Self::new([1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+
+    /// `[a, b, c, d, e, f]`
+    pub const fn new(coefficients: [f32; 6]) -> Self {
+        Self { coefficients }
+    }
+
+    pub fn coefficients(&self) -> [f32; 6] {
+        self.coefficients
+    }
+
+    /// Least squares fit through `(touch, panel)` pairs, needs at least three
+    /// that aren't on one line.
+    pub fn from_points(samples: &[(Point, Point)]) -> Option<Self> {
+        if samples.len() < 3 {
+            return None;
+        }
+
+        // Centred on the mean touch to keep the sums small
+        let n = samples.len() as f32;
+        let mean =
+            |f: fn(&(Point, Point)) -> i32| samples.iter().map(|s| f(s) as f32).sum::<f32>() / n;
+        let (mx, my) = (mean(|s| s.0.x), mean(|s| s.0.y));
+        let (mx2, my2) = (mean(|s| s.1.x), mean(|s| s.1.y));
+
+        let (mut uu, mut uv, mut vv) = (0.0, 0.0, 0.0);
+        let (mut ux, mut vx, mut uy, mut vy) = (0.0, 0.0, 0.0, 0.0);
+        for (touch, panel) in samples {
+            let (u, v) = (touch.x as f32 - mx, touch.y as f32 - my);
+            let (x, y) = (panel.x as f32 - mx2, panel.y as f32 - my2);
+            uu += u * u;
+            uv += u * v;
+            vv += v * v;
+            ux += u * x;
+            vx += v * x;
+            uy += u * y;
+            vy += v * y;
+        }
+
+        let det = uu * vv - uv * uv;
+        if det.abs() < 1e-3 * (uu * vv).max(1.0) {
+            return None;
+        }
+        let solve = |bu: f32, bv: f32| ((bu * vv - bv * uv) / det, (bv * uu - bu * uv) / det);
+        let (a, b) = solve(ux, vx);
+        let (d, e) = solve(uy, vy);
+
+        Some(Self::new([
+            a,
+            b,
+            mx2 - a * mx - b * my,
+            d,
+            e,
+            my2 - d * mx - e * my,
+        ]))
+    }
+
+    /// Raw touch position to panel position.
+    pub fn apply(&self, point: Point) -> Point {
+        let [a, b, c, d, e, f] = self.coefficients;
+        let (x, y) = (point.x as f32, point.y as f32);
+        Point::new(
+            libm::roundf(a * x + b * y + c) as i32,
+            libm::roundf(d * x + e * y + f) as i32,
+        )
+    }
+
+    /// For storing in flash, little endian.
+    pub fn to_bytes(&self) -> [u8; CALIBRATION_LEN] {
+        let mut bytes = [0; CALIBRATION_LEN];
+        bytes[0] = MAGIC;
+        bytes[1] = VERSION;
+        for (chunk, coefficient) in bytes[2..].chunks_exact_mut(4).zip(self.coefficients) {
+            chunk.copy_from_slice(&coefficient.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// None if `bytes` isn't something `to_bytes` wrote, e.g. erased flash.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..CALIBRATION_LEN)?;
+        if bytes[0] != MAGIC || bytes[1] != VERSION {
+            return None;
+        }
+
+        let mut coefficients = [0.0; 6];
+        for (coefficient, chunk) in coefficients.iter_mut().zip(bytes[2..].chunks_exact(4)) {
+            *coefficient = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        coefficients
+            .iter()
+            .all(|c| c.is_finite())
+            .then_some(Self::new(coefficients))
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[cfg(target_arch = "xtensa")]
+/// Asks the user to touch a few crosshairs and fits a calibration to it.
+/// The result is also set on `touch`; the screen is left cleared.
+pub async fn calibrate<B, T, C>(
+    touch: &mut Touch<'_>,
+    display: &mut Spd2010<B, T, C>,
+    color: C,
+    background: C,
+) -> Result<Calibration, CalibrationError<B::Error>>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    // Sample raw positions, targets are converted to panel space instead.
+    // The filter would smooth the samples and mask targets near the edge.
+    let (orientation, previous) = (touch.orientation(), touch.calibration());
+    touch.set_orientation(Orientation::default());
+    touch.set_calibration(Calibration::IDENTITY);
+    let filter = touch.set_filter(None);
+
+    let samples = collect_samples(touch, display, color, background).await;
+    touch.set_orientation(orientation);
+    touch.set_calibration(previous);
+    touch.set_filter(filter);
+    if let Some(filter) = touch.filter_mut() {
+        filter.reset();
+    }
+    let Ok(()) = display.clear(background);
+    let samples = samples?;
+    display
+        .flush_dirty()
+        .await
+        .map_err(CalibrationError::Display)?;
+
+    let calibration = Calibration::from_points(&samples).ok_or(CalibrationError::Degenerate)?;
+    touch.set_calibration(calibration);
+    Ok(calibration)
+}
+
+#[cfg(target_arch = "xtensa")]
+async fn collect_samples<B, T, C>(
+    touch: &mut Touch<'_>,
+    display: &mut Spd2010<B, T, C>,
+    color: C,
+    background: C,
+) -> Result<[(Point, Point); TARGETS.len()], CalibrationError<B::Error>>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    let transform = display.orientation().transform();
+    let mut samples = [(Point::zero(), Point::zero()); TARGETS.len()];
+    for (sample, (angle, radius)) in samples.iter_mut().zip(TARGETS) {
+        let target = round::polar(angle, radius);
+        let raw = sample_target(touch, display, target, color, background).await?;
+        *sample = (raw, transform.map_point(target));
+    }
+    Ok(samples)
+}
+
+#[cfg(target_arch = "xtensa")]
+// Shows a crosshair on `target` and averages where the first finger was from
+// touching down to lifting off
+async fn sample_target<B, T, C>(
+    touch: &mut Touch<'_>,
+    display: &mut Spd2010<B, T, C>,
+    target: Point,
+    color: C,
+    background: C,
+) -> Result<Point, CalibrationError<B::Error>>
+where
+    B: QspiTransport,
+    T: TearingEffect,
+    C: PixelFormat,
+{
+    let style = PrimitiveStyle::with_stroke(color, 1);
+    let half = TARGET_SIZE as i32 / 2;
+    let Ok(()) = display.clear(background);
+    let Ok(()) = Line::new(target - Point::new(half, 0), target + Point::new(half, 0))
+        .into_styled(style)
+        .draw(display);
+    let Ok(()) = Line::new(target - Point::new(0, half), target + Point::new(0, half))
+        .into_styled(style)
+        .draw(display);
+    let Ok(()) = Circle::with_center(target, TARGET_SIZE / 2)
+        .into_styled(style)
+        .draw(display);
+    display
+        .flush_dirty()
+        .await
+        .map_err(CalibrationError::Display)?;
+
+    let events = touch.events();
+    while events.try_receive().is_ok() {}
+
+    let mut sum = Point::zero();
+    let mut count = 0;
+    loop {
+        touch.update().await.map_err(CalibrationError::Touch)?;
+        while let Ok(event) = events.try_receive() {
+            if event.id != 0 {
+                continue;
+            }
+            match event.phase {
+                TouchPhase::Down | TouchPhase::Move => {
+                    sum += event.position;
+                    count += 1;
+                }
+                TouchPhase::Up if count > 0 => return Ok(sum / count),
+                TouchPhase::Up => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2% wider, a little rotated and offset, as a digitiser might be
+    const SKEWED: Calibration = Calibration::new([1.02, 0.03, -5.0, -0.02, 0.98, 7.0]);
+
+    fn samples(calibration: &Calibration, touches: &[Point]) -> Vec<(Point, Point)> {
+        touches
+            .iter()
+            .map(|&touch| (touch, calibration.apply(touch)))
+            .collect()
+    }
+
+    fn targets() -> [Point; 5] {
+        [
+            Point::new(206, 206),
+            Point::new(305, 107),
+            Point::new(305, 305),
+            Point::new(107, 305),
+            Point::new(107, 107),
+        ]
+    }
+
+    #[test]
+    fn fits_a_known_transform() {
+        let fitted = Calibration::from_points(&samples(&SKEWED, &targets())).unwrap();
+        let expected = SKEWED.coefficients();
+        for (i, (fitted, expected)) in fitted.coefficients().iter().zip(expected).enumerate() {
+            // Offsets absorb the rounding of the samples
+            let tolerance = if i % 3 == 2 { 1.0 } else { 0.01 };
+            assert!((fitted - expected).abs() < tolerance, "{i}: {fitted}");
+        }
+
+        // A pure offset fits exactly
+        let offset = Calibration::new([1.0, 0.0, 10.0, 0.0, 1.0, -4.0]);
+        let fitted = Calibration::from_points(&samples(&offset, &targets())).unwrap();
+        assert_eq!(fitted.apply(Point::new(50, 60)), Point::new(60, 56));
+    }
+
+    #[test]
+    fn spreads_one_bad_sample_over_the_fit() {
+        let mut samples = samples(&Calibration::IDENTITY, &targets());
+        // The centre touch 5px off
+        samples[0].1 += Point::new(5, 0);
+        let fitted = Calibration::from_points(&samples).unwrap();
+
+        let residuals: Vec<i32> = samples
+            .iter()
+            .map(|(touch, panel)| (fitted.apply(*touch) - *panel).x.abs())
+            .collect();
+        // Least squares splits the error instead of matching four points
+        // exactly and missing the fifth by 5
+        assert_eq!(residuals, [4, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn rejects_degenerate_samples() {
+        // Too few
+        let two = samples(&SKEWED, &targets()[..2]);
+        assert_eq!(Calibration::from_points(&two), None);
+
+        // All on one line, diagonal or straight
+        let diagonal = [
+            Point::new(10, 10),
+            Point::new(100, 100),
+            Point::new(300, 300),
+        ];
+        assert_eq!(Calibration::from_points(&samples(&SKEWED, &diagonal)), None);
+        let row = [Point::new(10, 50), Point::new(100, 50), Point::new(300, 50)];
+        assert_eq!(Calibration::from_points(&samples(&SKEWED, &row)), None);
+
+        // The same touch over and over
+        let same = [Point::new(206, 206); 5];
+        assert_eq!(Calibration::from_points(&samples(&SKEWED, &same)), None);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let bytes = SKEWED.to_bytes();
+        assert_eq!(bytes.len(), CALIBRATION_LEN);
+        assert_eq!(&bytes[..2], &[MAGIC, VERSION]);
+        assert_eq!(Calibration::from_bytes(&bytes), Some(SKEWED));
+
+        // Trailing bytes, e.g. the rest of a flash sector, are ignored
+        let mut sector = [0xFF; 64];
+        sector[..CALIBRATION_LEN].copy_from_slice(&bytes);
+        assert_eq!(Calibration::from_bytes(&sector), Some(SKEWED));
+    }
+
+    #[test]
+    fn rejects_bytes_it_did_not_write() {
+        let bytes = SKEWED.to_bytes();
+        assert_eq!(Calibration::from_bytes(&bytes[..CALIBRATION_LEN - 1]), None);
+        assert_eq!(Calibration::from_bytes(&[]), None);
+        // Erased flash
+        assert_eq!(Calibration::from_bytes(&[0xFF; CALIBRATION_LEN]), None);
+
+        let mut wrong_version = bytes;
+        wrong_version[1] = VERSION + 1;
+        assert_eq!(Calibration::from_bytes(&wrong_version), None);
+
+        let mut not_finite = bytes;
+        not_finite[2..6].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert_eq!(Calibration::from_bytes(&not_finite), None);
+        not_finite[2..6].copy_from_slice(&f32::INFINITY.to_le_bytes());
+        assert_eq!(Calibration::from_bytes(&not_finite), None);
+    }
+}
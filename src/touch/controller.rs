@@ -0,0 +1,181 @@
+// Owns the SPD2010 touch controller and turns its reports into events.
+// Instead of polling the controller it sleeps on `TOUCH_SIGNAL` until the INT
+// line fires. With a `'static` I2C bus the whole thing can run in its own task:
+//
+//     static TOUCH_EVENTS: TouchChannel = Channel::new();
+//
+//     #[embassy_executor::task]
+//     async fn touch(touch: Touch<'static>) {
+//         touch.run().await
+//     }
+//
+// Otherwise call `update` from a loop and drain the channel in between.
+
+use embassy_time::{Delay, Instant};
+use embedded_graphics::prelude::Point;
+use esp_hal::{Async, i2c::master::I2c};
+use spd2010::touch::{SPD2010Touch, TouchData};
+
+use crate::{display::orientation::Orientation, println};
+
+use super::{
+    calibration::Calibration,
+    event::{MAX_TOUCH_POINTS, TouchChannel, TouchEvent, TouchFrame, TouchPhase},
+    filter::TouchFilter,
+    interrupt::{TOUCH_INTERRUPT, TOUCH_SIGNAL, TouchInterrupt},
+    record::TouchRecorder,
+};
+
+pub type Controller<'a> = SPD2010Touch<'a, I2c<'static, Async>, TouchInterrupt<'static>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchError {
+    /// Reading the report over I2C failed
+    Bus,
+}
+
+pub struct Touch<'a> {
+    controller: Controller<'a>,
+    events: &'static TouchChannel,
+    calibration: Calibration,
+    // Of the display, positions are reported the way the UI sees them
+    orientation: Orientation,
+    filter: Option<TouchFilter>,
+    recorder: Option<TouchRecorder>,
+    // What was reported last time
+    last: TouchFrame,
+}
+
+impl<'a> Touch<'a> {
+    /// `interrupt::install` must have been called first.
+    pub fn new(i2c: &'a mut I2c<'static, Async>, events: &'static TouchChannel) -> Self {
+        Self {
+            controller: SPD2010Touch::new(i2c, &TOUCH_INTERRUPT),
+            events,
+            calibration: Calibration::IDENTITY,
+            orientation: Orientation::default(),
+            filter: None,
+            recorder: None,
+            last: [None; MAX_TOUCH_POINTS],
+        }
+    }
+
+    /// For anything not wrapped here, e.g. the firmware version.
+    pub fn controller_mut(&mut self) -> &mut Controller<'a> {
+        &mut self.controller
+    }
+
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Keep in step with `Spd2010::set_orientation`.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Runs every report through `filter` before it becomes events.
+    pub fn with_filter(mut self, filter: TouchFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn filter_mut(&mut self) -> Option<&mut TouchFilter> {
+        self.filter.as_mut()
+    }
+
+    /// Returns the filter that was set before.
+    pub fn set_filter(&mut self, filter: Option<TouchFilter>) -> Option<TouchFilter> {
+        core::mem::replace(&mut self.filter, filter)
+    }
+
+    /// Records every event published from now on, replacing any recording
+    /// in progress.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(TouchRecorder::new());
+    }
+
+    pub fn stop_recording(&mut self) -> Option<TouchRecorder> {
+        self.recorder.take()
+    }
+
+    pub fn recorder(&self) -> Option<&TouchRecorder> {
+        self.recorder.as_ref()
+    }
+
+    pub fn events(&self) -> &'static TouchChannel {
+        self.events
+    }
+
+    /// Waits for the controller to have a report.
+    pub async fn wait(&mut self) {
+        while !self.controller.available() {
+            TOUCH_SIGNAL.wait().await;
+        }
+    }
+
+    /// Waits for a report and publishes what changed since the last one.
+    pub async fn update(&mut self) -> Result<(), TouchError> {
+        self.wait().await;
+
+        let mut data = TouchData::default();
+        self.controller
+            .read(&mut Delay, &mut data)
+            .await
+            .map_err(|_| TouchError::Bus)?;
+        let time = Instant::now();
+
+        let transform = self.orientation.transform();
+        let mut current: TouchFrame = [None; MAX_TOUCH_POINTS];
+        for (slot, point) in data.points.iter().take(MAX_TOUCH_POINTS).enumerate() {
+            let raw = Point::new(point.x as i32, point.y as i32);
+            current[slot] = Some(transform.unmap_point(self.calibration.apply(raw)));
+        }
+        if let Some(filter) = self.filter.as_mut() {
+            current = filter.apply(time, &current);
+        }
+
+        for (id, (last, current)) in self.last.iter().zip(current.iter()).enumerate() {
+            let (phase, position) = match (*last, *current) {
+                (None, Some(position)) => (TouchPhase::Down, position),
+                (Some(last), Some(position)) if last != position => (TouchPhase::Move, position),
+                (Some(last), None) => (TouchPhase::Up, last),
+                _ => continue,
+            };
+            let event = TouchEvent {
+                phase,
+                id: id as u8,
+                position,
+                time,
+            };
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.record(&event);
+            }
+            self.events.send(event).await;
+        }
+
+        self.last = current;
+        Ok(())
+    }
+
+    pub async fn run(mut self) -> ! {
+        loop {
+            if let Err(e) = self.update().await {
+                println!("Touch: {:?}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::Instant;
+use embedded_graphics::prelude::Point;
+
+// Points the SPD2010 reports at once
+pub const MAX_TOUCH_POINTS: usize = 10;
+const EVENT_QUEUE_DEPTH: usize = 16;
+
+/// Where each finger is in one report, by slot.
+pub type TouchFrame = [Option<Point>; MAX_TOUCH_POINTS];
+
+/// Events from `Touch::run`, usually a `static`.
+pub type TouchChannel = Channel<CriticalSectionRawMutex, TouchEvent, EVENT_QUEUE_DEPTH>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchPhase {
+    Down,
+    Move,
+    /// `position` is where the finger was last seen
+    Up,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TouchEvent {
+    pub phase: TouchPhase,
+    /// Which finger, stable from `Down` to `Up`
+    pub id: u8,
+    /// In UI coordinates, after calibration and rotation
+    pub position: Point,
+    /// When the report was read
+    pub time: Instant,
+}
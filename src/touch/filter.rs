@@ -0,0 +1,421 @@
+// Cleans up touch reports before they become events. Raw SPD2010 positions
+// jitter by a few pixels, and the bezel of the round screen picks up touches
+// nobody meant. Works on whole reports (where every finger is at one instant)
+// so a finger can be held back or dropped before anyone sees its `Down`.
+//
+// Pure logic on the report times, `Touch::with_filter` puts it in the path.
+
+use embassy_time::{Duration, Instant};
+use embedded_graphics::prelude::Point;
+
+use super::event::{MAX_TOUCH_POINTS, TouchFrame};
+use crate::display::round;
+
+/// Low pass filter whose cutoff rises with speed: smooth when the finger
+/// rests, little lag when it moves. See Casiez et al., "1€ Filter".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OneEuroFilter {
+    /// Hz, lower is smoother at rest
+    pub min_cutoff: f32,
+    /// How fast the cutoff rises with speed, higher lags less
+    pub beta: f32,
+    /// Hz, for the speed estimate
+    pub derivative_cutoff: f32,
+    // Filtered value and speed, None until the first sample
+    state: Option<(f32, f32)>,
+}
+
+impl OneEuroFilter {
+    pub const fn new(min_cutoff: f32, beta: f32) -> Self {
+        Self {
+            min_cutoff,
+            beta,
+            derivative_cutoff: 1.0,
+            state: None,
+        }
+    }
+
+    /// Starts over, the next sample passes through unchanged.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    /// `dt` is the time since the previous sample, in seconds.
+    pub fn filter(&mut self, value: f32, dt: f32) -> f32 {
+        let Some((previous, speed)) = self.state else {
+            self.state = Some((value, 0.0));
+            return value;
+        };
+        if dt <= 0.0 {
+            return previous;
+        }
+
+        let raw_speed = (value - previous) / dt;
+        let speed = lerp(speed, raw_speed, alpha(self.derivative_cutoff, dt));
+        let cutoff = self.min_cutoff + self.beta * speed.abs();
+        let value = lerp(previous, value, alpha(cutoff, dt));
+        self.state = Some((value, speed));
+        value
+    }
+}
+
+fn alpha(cutoff: f32, dt: f32) -> f32 {
+    let tau = 1.0 / (2.0 * core::f32::consts::PI * cutoff);
+    1.0 / (1.0 + tau / dt)
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterConfig {
+    /// One euro filter parameters, `min_cutoff` 0.0 turns smoothing off
+    pub min_cutoff: f32,
+    pub beta: f32,
+    /// Smaller moves than this (in pixels) aren't reported
+    pub min_movement: u32,
+    /// How long a finger has to stay down before it counts
+    pub debounce: Duration,
+    /// More fingers than this at once is a palm, everything is dropped
+    /// until the screen is clear again
+    pub max_contacts: usize,
+    /// Fingers landing within this many pixels of the edge of the visible
+    /// circle are ignored
+    pub edge_margin: u32,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            min_cutoff: 1.0,
+            beta: 0.01,
+            min_movement: 2,
+            debounce: Duration::from_millis(20),
+            max_contacts: 3,
+            edge_margin: 4,
+        }
+    }
+}
+
+impl FilterConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_smoothing(mut self, min_cutoff: f32, beta: f32) -> Self {
+        self.min_cutoff = min_cutoff;
+        self.beta = beta;
+        self
+    }
+
+    pub fn with_min_movement(mut self, min_movement: u32) -> Self {
+        self.min_movement = min_movement;
+        self
+    }
+
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn with_max_contacts(mut self, max_contacts: usize) -> Self {
+        self.max_contacts = max_contacts;
+        self
+    }
+
+    pub fn with_edge_margin(mut self, edge_margin: u32) -> Self {
+        self.edge_margin = edge_margin;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Empty,
+    /// Down, but not for long enough yet
+    Pending {
+        since: Instant,
+    },
+    /// Ignored until it lifts
+    Rejected,
+    Tracking {
+        x: OneEuroFilter,
+        y: OneEuroFilter,
+        time: Instant,
+        reported: Point,
+    },
+}
+
+pub struct TouchFilter {
+    config: FilterConfig,
+    slots: [Slot; MAX_TOUCH_POINTS],
+    // A palm was seen, nothing counts until every finger is up
+    palm: bool,
+}
+
+impl TouchFilter {
+    pub fn new(config: FilterConfig) -> Self {
+        Self {
+            config,
+            slots: [Slot::Empty; MAX_TOUCH_POINTS],
+            palm: false,
+        }
+    }
+
+    pub fn config(&self) -> &FilterConfig {
+        &self.config
+    }
+
+    /// Smoothing and the edge margin are picked up by fingers that land from
+    /// now on. The debounce, `min_movement` and `max_contacts` apply to the
+    /// fingers already down as well.
+    pub fn set_config(&mut self, config: FilterConfig) {
+        self.config = config;
+    }
+
+    pub fn reset(&mut self) {
+        self.slots = [Slot::Empty; MAX_TOUCH_POINTS];
+        self.palm = false;
+    }
+
+    /// Filters one report taken at `time`. A finger that's held back or
+    /// rejected is missing from the result.
+    pub fn apply(&mut self, time: Instant, frame: &TouchFrame) -> TouchFrame {
+        let contacts = frame.iter().flatten().count();
+        if contacts == 0 {
+            self.palm = false;
+        } else if contacts > self.config.max_contacts {
+            self.palm = true;
+        }
+
+        let mut filtered = [None; MAX_TOUCH_POINTS];
+        for ((slot, raw), out) in self.slots.iter_mut().zip(frame).zip(filtered.iter_mut()) {
+            let Some(raw) = *raw else {
+                *slot = Slot::Empty;
+                continue;
+            };
+            if self.palm {
+                *slot = Slot::Rejected;
+                continue;
+            }
+
+            if *slot == Slot::Empty {
+                *slot = match in_bounds(raw, self.config.edge_margin) {
+                    true => Slot::Pending { since: time },
+                    false => Slot::Rejected,
+                };
+            }
+            if let Slot::Pending { since } = *slot {
+                if time - since < self.config.debounce {
+                    continue;
+                }
+                let smoothing = OneEuroFilter::new(self.config.min_cutoff, self.config.beta);
+                *slot = Slot::Tracking {
+                    x: smoothing,
+                    y: smoothing,
+                    time,
+                    reported: raw,
+                };
+            }
+            if let Slot::Tracking {
+                x,
+                y,
+                time: last,
+                reported,
+            } = slot
+            {
+                let dt = (time - *last).as_micros() as f32 / 1_000_000.0;
+                *last = time;
+                // Smoothing as it was when the finger landed
+                let smoothed = match x.min_cutoff > 0.0 {
+                    true => Point::new(
+                        libm::roundf(x.filter(raw.x as f32, dt)) as i32,
+                        libm::roundf(y.filter(raw.y as f32, dt)) as i32,
+                    ),
+                    false => raw,
+                };
+                let moved = smoothed - *reported;
+                let threshold = self.config.min_movement as i32;
+                if moved.x * moved.x + moved.y * moved.y >= threshold * threshold {
+                    *reported = smoothed;
+                }
+                *out = Some(*reported);
+            }
+        }
+
+        filtered
+    }
+}
+
+impl Default for TouchFilter {
+    fn default() -> Self {
+        Self::new(FilterConfig::default())
+    }
+}
+
+// Inside the visible circle and at least `margin` pixels from its edge
+fn in_bounds(point: Point, margin: u32) -> bool {
+    let radius = round::RADIUS.saturating_sub(margin) as i32;
+    let d = point - round::CENTER;
+    d.x * d.x + d.y * d.y <= radius * radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: u64) -> Instant {
+        Instant::from_millis(millis)
+    }
+
+    fn frame(points: &[(usize, i32, i32)]) -> TouchFrame {
+        let mut frame = [None; MAX_TOUCH_POINTS];
+        for &(slot, x, y) in points {
+            frame[slot] = Some(Point::new(x, y));
+        }
+        frame
+    }
+
+    // Past the debounce, so later reports come straight through
+    fn tracking(filter: &mut TouchFilter, x: i32, y: i32) {
+        filter.apply(at(0), &frame(&[(0, x, y)]));
+        let reported = filter.apply(at(20), &frame(&[(0, x, y)]));
+        assert_eq!(reported[0], Some(Point::new(x, y)));
+    }
+
+    #[test]
+    fn one_euro_smooths_at_rest() {
+        let mut filter = OneEuroFilter::new(1.0, 0.0);
+        assert_eq!(filter.filter(100.0, 0.01), 100.0);
+        // A 10 pixel jump at rest only moves a little at a 1 Hz cutoff
+        let value = filter.filter(110.0, 0.01);
+        assert!(value > 100.0 && value < 101.0, "{value}");
+
+        filter.reset();
+        assert_eq!(filter.filter(110.0, 0.01), 110.0);
+    }
+
+    #[test]
+    fn one_euro_follows_fast_moves() {
+        let (mut slow, mut fast) = (OneEuroFilter::new(1.0, 0.0), OneEuroFilter::new(1.0, 1.0));
+        let (mut a, mut b) = (0.0, 0.0);
+        for i in 0..10 {
+            let x = i as f32 * 20.0;
+            a = slow.filter(x, 0.01);
+            b = fast.filter(x, 0.01);
+        }
+        // The speed raises the cutoff, so less lag behind 180
+        assert!(180.0 - b < (180.0 - a) / 4.0, "{a} {b}");
+    }
+
+    #[test]
+    fn damps_jitter() {
+        let mut filter = TouchFilter::default();
+        tracking(&mut filter, 200, 200);
+
+        for (i, (x, y)) in [(201, 200), (199, 201), (200, 199), (201, 201)]
+            .into_iter()
+            .enumerate()
+        {
+            let reported = filter.apply(at(30 + 10 * i as u64), &frame(&[(0, x, y)]));
+            assert_eq!(reported[0], Some(Point::new(200, 200)));
+        }
+
+        // A real move gets through
+        let mut reported = None;
+        for i in 0..20 {
+            reported = filter.apply(at(100 + 10 * i), &frame(&[(0, 260, 200)]))[0];
+        }
+        assert!(reported.unwrap().x > 250, "{reported:?}");
+    }
+
+    #[test]
+    fn raw_without_smoothing() {
+        let config = FilterConfig::new()
+            .with_smoothing(0.0, 0.0)
+            .with_min_movement(0);
+        let mut filter = TouchFilter::new(config);
+        tracking(&mut filter, 200, 200);
+        let reported = filter.apply(at(30), &frame(&[(0, 201, 199)]));
+        assert_eq!(reported[0], Some(Point::new(201, 199)));
+    }
+
+    #[test]
+    fn debounces_new_fingers() {
+        let mut filter = TouchFilter::default();
+        assert_eq!(filter.apply(at(0), &frame(&[(0, 200, 200)]))[0], None);
+        assert_eq!(filter.apply(at(10), &frame(&[(0, 200, 200)]))[0], None);
+        assert_eq!(
+            filter.apply(at(20), &frame(&[(0, 200, 200)]))[0],
+            Some(Point::new(200, 200))
+        );
+
+        // Lifting early means it never happened
+        filter.apply(at(30), &frame(&[]));
+        assert_eq!(filter.apply(at(40), &frame(&[(0, 150, 150)]))[0], None);
+        filter.apply(at(50), &frame(&[]));
+        assert_eq!(filter.apply(at(60), &frame(&[(0, 150, 150)]))[0], None);
+    }
+
+    #[test]
+    fn masks_the_edge() {
+        let config = FilterConfig::new().with_debounce(Duration::from_ticks(0));
+        let mut filter = TouchFilter::new(config);
+        let edge = round::CENTER.x + round::RADIUS as i32 - 2;
+        let inside = round::CENTER.x + round::RADIUS as i32 - 4;
+
+        let reported = filter.apply(at(0), &frame(&[(0, edge, 206), (1, inside, 206)]));
+        assert_eq!(reported[0], None);
+        assert_eq!(reported[1], Some(Point::new(inside, 206)));
+
+        // Stays rejected after moving in, until it lifts
+        let reported = filter.apply(at(10), &frame(&[(0, 206, 206)]));
+        assert_eq!(reported[0], None);
+        filter.apply(at(20), &frame(&[]));
+        let reported = filter.apply(at(30), &frame(&[(0, 206, 206)]));
+        assert_eq!(reported[0], Some(Point::new(206, 206)));
+
+        // Corners are off the round screen altogether
+        assert_eq!(filter.apply(at(40), &frame(&[(1, 5, 5)]))[1], None);
+    }
+
+    #[test]
+    fn rejects_palms() {
+        let mut filter = TouchFilter::default();
+        tracking(&mut filter, 200, 200);
+
+        let palm = frame(&[(0, 200, 200), (1, 220, 200), (2, 240, 200), (3, 260, 200)]);
+        assert_eq!(filter.apply(at(30), &palm), [None; MAX_TOUCH_POINTS]);
+
+        // Nothing counts, not even the fingers left behind, until the screen
+        // is clear
+        let one = frame(&[(0, 200, 200)]);
+        assert_eq!(filter.apply(at(100), &one), [None; MAX_TOUCH_POINTS]);
+        filter.apply(at(110), &frame(&[]));
+        filter.apply(at(120), &one);
+        assert_eq!(filter.apply(at(140), &one)[0], Some(Point::new(200, 200)));
+    }
+
+    #[test]
+    fn new_config_applies_to_fingers_already_down() {
+        let unsmoothed = FilterConfig::new().with_smoothing(0.0, 0.0);
+        let mut filter = TouchFilter::new(unsmoothed.with_min_movement(0));
+        tracking(&mut filter, 100, 100);
+
+        // A larger min_movement holds back the finger already down
+        filter.set_config(unsmoothed.with_min_movement(10));
+        let reported = filter.apply(at(30), &frame(&[(0, 105, 100)]));
+        assert_eq!(reported[0], Some(Point::new(100, 100)));
+
+        // Smoothing doesn't, it's only set up when a finger lands
+        filter.set_config(FilterConfig::new().with_min_movement(0));
+        let reported = filter.apply(at(40), &frame(&[(0, 120, 100)]));
+        assert_eq!(reported[0], Some(Point::new(120, 100)));
+
+        // Fewer contacts allowed turns the finger into a palm right away
+        filter.set_config(unsmoothed.with_max_contacts(0));
+        let reported = filter.apply(at(50), &frame(&[(0, 120, 100)]));
+        assert_eq!(reported[0], None);
+    }
+}
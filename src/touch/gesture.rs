@@ -0,0 +1,649 @@
+// Turns the down/move/up events from `Touch` into gestures. Pure logic on the
+// timestamps in the events, so recorded traces give the same result anywhere.
+//
+// Feed every event to `GestureRecognizer::process` and call `tick` now and then
+// while nothing happens, a long press fires without an event to drive it.
+
+use embassy_time::{Duration, Instant};
+use embedded_graphics::prelude::Point;
+use heapless::Vec;
+
+use super::event::{TouchEvent, TouchPhase};
+
+// Fingers tracked at once, the rest are ignored
+const MAX_CONTACTS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// Reported as soon as the finger lifts, even if a double tap follows
+    Tap(Point),
+    /// Second tap, instead of a `Tap`
+    DoubleTap(Point),
+    LongPress(Point),
+    /// Drag that ended fast enough, instead of `DragEnd`
+    Swipe {
+        direction: SwipeDirection,
+        start: Point,
+        end: Point,
+        /// Pixels per second
+        velocity: f32,
+    },
+    /// The finger moved out of the tap slop
+    DragStart(Point),
+    Drag {
+        position: Point,
+        /// Since the previous `Drag` or `DragStart`
+        delta: Point,
+    },
+    DragEnd(Point),
+    /// Two fingers, relative to where they landed
+    Pinch {
+        center: Point,
+        /// Finger distance over the starting distance
+        scale: f32,
+        /// Degrees, clockwise positive
+        rotation: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    /// How far a tap or long press may wander
+    pub tap_slop: u32,
+    pub max_tap_duration: Duration,
+    /// Between the first tap's release and the second tap's
+    pub double_tap_interval: Duration,
+    /// How far apart the two taps of a double tap may be
+    pub double_tap_slop: u32,
+    pub long_press: Duration,
+    pub swipe_min_distance: u32,
+    /// Pixels per second
+    pub swipe_min_velocity: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap_slop: 10,
+            max_tap_duration: Duration::from_millis(300),
+            double_tap_interval: Duration::from_millis(300),
+            double_tap_slop: 30,
+            long_press: Duration::from_millis(600),
+            swipe_min_distance: 40,
+            swipe_min_velocity: 300.0,
+        }
+    }
+}
+
+impl GestureConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tap_slop(mut self, tap_slop: u32) -> Self {
+        self.tap_slop = tap_slop;
+        self
+    }
+
+    pub fn with_max_tap_duration(mut self, max_tap_duration: Duration) -> Self {
+        self.max_tap_duration = max_tap_duration;
+        self
+    }
+
+    pub fn with_double_tap(mut self, interval: Duration, slop: u32) -> Self {
+        self.double_tap_interval = interval;
+        self.double_tap_slop = slop;
+        self
+    }
+
+    pub fn with_long_press(mut self, long_press: Duration) -> Self {
+        self.long_press = long_press;
+        self
+    }
+
+    pub fn with_swipe(mut self, min_distance: u32, min_velocity: f32) -> Self {
+        self.swipe_min_distance = min_distance;
+        self.swipe_min_velocity = min_velocity;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Contact {
+    id: u8,
+    start: Point,
+    start_time: Instant,
+    position: Point,
+    // Where the last `Drag` was reported
+    reported: Point,
+    dragging: bool,
+    long_pressed: bool,
+}
+
+// Finger distance and angle when the second finger landed
+#[derive(Debug, Clone, Copy)]
+struct PinchStart {
+    distance: f32,
+    angle: f32,
+}
+
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    contacts: Vec<Contact, MAX_CONTACTS>,
+    // A second finger was down since the first one landed, no taps or swipes
+    multi: bool,
+    pinch: Option<PinchStart>,
+    last_tap: Option<(Point, Instant)>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            contacts: Vec::new(),
+            multi: false,
+            pinch: None,
+            last_tap: None,
+        }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: GestureConfig) {
+        self.config = config;
+    }
+
+    /// Forgets every finger, e.g. after the screen was switched off.
+    pub fn reset(&mut self) {
+        self.contacts.clear();
+        self.multi = false;
+        self.pinch = None;
+        self.last_tap = None;
+    }
+
+    pub fn process(&mut self, event: &TouchEvent) -> Option<Gesture> {
+        match event.phase {
+            TouchPhase::Down => self.down(event),
+            TouchPhase::Move => self.moved(event),
+            TouchPhase::Up => self.up(event),
+        }
+    }
+
+    /// Reports a long press once the finger has been held long enough.
+    pub fn tick(&mut self, now: Instant) -> Option<Gesture> {
+        if self.multi {
+            return None;
+        }
+        let config = self.config;
+        let contact = self.contacts.first_mut()?;
+        if contact.dragging
+            || contact.long_pressed
+            || now - contact.start_time < config.long_press
+            || distance(contact.start, contact.position) > config.tap_slop as f32
+        {
+            return None;
+        }
+        contact.long_pressed = true;
+        Some(Gesture::LongPress(contact.position))
+    }
+
+    fn down(&mut self, event: &TouchEvent) -> Option<Gesture> {
+        let contact = Contact {
+            id: event.id,
+            start: event.position,
+            start_time: event.time,
+            position: event.position,
+            reported: event.position,
+            dragging: false,
+            long_pressed: false,
+        };
+        if self.contacts.iter().any(|c| c.id == event.id) || self.contacts.push(contact).is_err() {
+            return None;
+        }
+
+        if let [a, b] = self.contacts.as_slice() {
+            self.multi = true;
+            self.pinch = Some(PinchStart {
+                distance: distance(a.position, b.position),
+                angle: angle(a.position, b.position),
+            });
+            // Ends a drag the first finger had going
+            if a.dragging {
+                return Some(Gesture::DragEnd(a.position));
+            }
+        }
+        None
+    }
+
+    fn moved(&mut self, event: &TouchEvent) -> Option<Gesture> {
+        let tap_slop = self.config.tap_slop as f32;
+        let contact = self.contacts.iter_mut().find(|c| c.id == event.id)?;
+        if contact.position == event.position {
+            return None;
+        }
+        contact.position = event.position;
+
+        if let ([a, b], Some(start)) = (self.contacts.as_slice(), self.pinch) {
+            let scale = if start.distance > 0.0 {
+                distance(a.position, b.position) / start.distance
+            } else {
+                1.0
+            };
+            return Some(Gesture::Pinch {
+                center: (a.position + b.position) / 2,
+                scale,
+                rotation: normalize_angle(angle(a.position, b.position) - start.angle),
+            });
+        }
+        if self.multi {
+            return None;
+        }
+
+        if let Some(gesture) = self.tick(event.time) {
+            return Some(gesture);
+        }
+
+        let contact = &mut self.contacts[0];
+        if contact.dragging {
+            let delta = contact.position - contact.reported;
+            contact.reported = contact.position;
+            Some(Gesture::Drag {
+                position: contact.position,
+                delta,
+            })
+        } else if distance(contact.start, contact.position) > tap_slop {
+            contact.dragging = true;
+            contact.reported = contact.start;
+            Some(Gesture::DragStart(contact.start))
+        } else {
+            None
+        }
+    }
+
+    fn up(&mut self, event: &TouchEvent) -> Option<Gesture> {
+        let index = self.contacts.iter().position(|c| c.id == event.id)?;
+        let contact = self.contacts.remove(index);
+
+        if self.multi {
+            self.pinch = None;
+            if self.contacts.is_empty() {
+                self.multi = false;
+            }
+            return None;
+        }
+
+        let position = event.position;
+        let held = event.time - contact.start_time;
+
+        if contact.dragging {
+            return Some(
+                self.swipe(&contact, position, held)
+                    .unwrap_or(Gesture::DragEnd(position)),
+            );
+        }
+        if contact.long_pressed {
+            return None;
+        }
+        if held >= self.config.long_press {
+            // Nobody called `tick` in time
+            return Some(Gesture::LongPress(position));
+        }
+        if held > self.config.max_tap_duration {
+            return None;
+        }
+
+        let double = self.last_tap.is_some_and(|(point, time)| {
+            event.time - time <= self.config.double_tap_interval
+                && distance(point, position) <= self.config.double_tap_slop as f32
+        });
+        if double {
+            self.last_tap = None;
+            Some(Gesture::DoubleTap(position))
+        } else {
+            self.last_tap = Some((position, event.time));
+            Some(Gesture::Tap(position))
+        }
+    }
+
+    fn swipe(&self, contact: &Contact, end: Point, held: Duration) -> Option<Gesture> {
+        let travelled = distance(contact.start, end);
+        let seconds = held.as_micros().max(1) as f32 / 1_000_000.0;
+        let velocity = travelled / seconds;
+        if travelled < self.config.swipe_min_distance as f32
+            || velocity < self.config.swipe_min_velocity
+        {
+            return None;
+        }
+
+        let delta = end - contact.start;
+        let direction = if delta.x.abs() >= delta.y.abs() {
+            match delta.x < 0 {
+                true => SwipeDirection::Left,
+                false => SwipeDirection::Right,
+            }
+        } else {
+            match delta.y < 0 {
+                true => SwipeDirection::Up,
+                false => SwipeDirection::Down,
+            }
+        };
+
+        Some(Gesture::Swipe {
+            direction,
+            start: contact.start,
+            end,
+            velocity,
+        })
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new(GestureConfig::default())
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    libm::hypotf((b.x - a.x) as f32, (b.y - a.y) as f32)
+}
+
+// Screen y points down, so this is clockwise
+fn angle(a: Point, b: Point) -> f32 {
+    libm::atan2f((b.y - a.y) as f32, (b.x - a.x) as f32).to_degrees()
+}
+
+// -180.0..180.0, without `rem_euclid` which core doesn't have for floats
+fn normalize_angle(degrees: f32) -> f32 {
+    let wrapped = libm::fmodf(degrees + 180.0, 360.0);
+    if wrapped < 0.0 {
+        wrapped + 180.0
+    } else {
+        wrapped - 180.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Trace {
+        recognizer: GestureRecognizer,
+        time: Instant,
+    }
+
+    impl Trace {
+        fn new() -> Self {
+            Self {
+                recognizer: GestureRecognizer::default(),
+                time: Instant::from_millis(1_000),
+            }
+        }
+
+        fn wait(&mut self, millis: u64) -> Option<Gesture> {
+            self.time += Duration::from_millis(millis);
+            self.recognizer.tick(self.time)
+        }
+
+        fn event(&mut self, phase: TouchPhase, id: u8, x: i32, y: i32) -> Option<Gesture> {
+            self.recognizer.process(&TouchEvent {
+                phase,
+                id,
+                position: Point::new(x, y),
+                time: self.time,
+            })
+        }
+
+        fn down(&mut self, id: u8, x: i32, y: i32) -> Option<Gesture> {
+            self.event(TouchPhase::Down, id, x, y)
+        }
+
+        fn moved(&mut self, id: u8, x: i32, y: i32) -> Option<Gesture> {
+            self.event(TouchPhase::Move, id, x, y)
+        }
+
+        fn up(&mut self, id: u8, x: i32, y: i32) -> Option<Gesture> {
+            self.event(TouchPhase::Up, id, x, y)
+        }
+    }
+
+    #[test]
+    fn tap() {
+        let mut trace = Trace::new();
+        assert_eq!(trace.down(0, 100, 100), None);
+        trace.wait(50);
+        assert_eq!(trace.moved(0, 103, 101), None);
+        trace.wait(50);
+        assert_eq!(
+            trace.up(0, 103, 101),
+            Some(Gesture::Tap(Point::new(103, 101)))
+        );
+    }
+
+    #[test]
+    fn slow_release_is_not_a_tap() {
+        let mut trace = Trace::new();
+        trace.down(0, 100, 100);
+        trace.wait(400);
+        assert_eq!(trace.up(0, 100, 100), None);
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut trace = Trace::new();
+        trace.down(0, 100, 100);
+        trace.wait(80);
+        assert_eq!(
+            trace.up(0, 100, 100),
+            Some(Gesture::Tap(Point::new(100, 100)))
+        );
+        trace.wait(150);
+        trace.down(0, 110, 105);
+        trace.wait(80);
+        assert_eq!(
+            trace.up(0, 110, 105),
+            Some(Gesture::DoubleTap(Point::new(110, 105)))
+        );
+
+        // A third tap starts over
+        trace.wait(100);
+        trace.down(0, 110, 105);
+        trace.wait(80);
+        assert_eq!(
+            trace.up(0, 110, 105),
+            Some(Gesture::Tap(Point::new(110, 105)))
+        );
+    }
+
+    #[test]
+    fn taps_too_far_apart_are_two_taps() {
+        let mut trace = Trace::new();
+        trace.down(0, 100, 100);
+        trace.wait(80);
+        trace.up(0, 100, 100);
+        trace.wait(150);
+        trace.down(0, 200, 100);
+        trace.wait(80);
+        assert_eq!(
+            trace.up(0, 200, 100),
+            Some(Gesture::Tap(Point::new(200, 100)))
+        );
+    }
+
+    #[test]
+    fn long_press() {
+        let mut trace = Trace::new();
+        trace.down(0, 100, 100);
+        assert_eq!(trace.wait(500), None);
+        assert_eq!(
+            trace.wait(100),
+            Some(Gesture::LongPress(Point::new(100, 100)))
+        );
+        // Only once, and no tap when it lifts
+        assert_eq!(trace.wait(100), None);
+        assert_eq!(trace.up(0, 100, 100), None);
+    }
+
+    #[test]
+    fn long_press_without_tick() {
+        let mut trace = Trace::new();
+        trace.down(0, 100, 100);
+        trace.time += Duration::from_millis(700);
+        assert_eq!(
+            trace.up(0, 102, 100),
+            Some(Gesture::LongPress(Point::new(102, 100)))
+        );
+    }
+
+    #[test]
+    fn swipe() {
+        let mut trace = Trace::new();
+        trace.down(0, 300, 200);
+        trace.wait(20);
+        assert_eq!(
+            trace.moved(0, 280, 202),
+            Some(Gesture::DragStart(Point::new(300, 200)))
+        );
+        trace.wait(20);
+        trace.moved(0, 240, 204);
+        trace.wait(20);
+        trace.moved(0, 200, 206);
+        trace.wait(40);
+
+        let Some(Gesture::Swipe {
+            direction,
+            start,
+            end,
+            velocity,
+        }) = trace.up(0, 200, 206)
+        else {
+            panic!("no swipe");
+        };
+        assert_eq!(direction, SwipeDirection::Left);
+        assert_eq!((start, end), (Point::new(300, 200), Point::new(200, 206)));
+        // About 100 pixels in 100 ms
+        assert!((velocity - 1001.8).abs() < 1.0, "{velocity}");
+    }
+
+    #[test]
+    fn swipe_directions() {
+        for (dx, dy, direction) in [
+            (0, -80, SwipeDirection::Up),
+            (0, 80, SwipeDirection::Down),
+            (-80, 0, SwipeDirection::Left),
+            (80, 0, SwipeDirection::Right),
+        ] {
+            let mut trace = Trace::new();
+            trace.down(0, 200, 200);
+            trace.wait(50);
+            trace.moved(0, 200 + dx, 200 + dy);
+            let gesture = trace.up(0, 200 + dx, 200 + dy);
+            assert!(
+                matches!(gesture, Some(Gesture::Swipe { direction: d, .. }) if d == direction),
+                "{gesture:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn drag() {
+        let mut trace = Trace::new();
+        trace.down(0, 100, 100);
+        trace.wait(100);
+        assert_eq!(trace.moved(0, 105, 100), None);
+        trace.wait(100);
+        assert_eq!(
+            trace.moved(0, 120, 100),
+            Some(Gesture::DragStart(Point::new(100, 100)))
+        );
+        trace.wait(100);
+        assert_eq!(
+            trace.moved(0, 130, 110),
+            Some(Gesture::Drag {
+                position: Point::new(130, 110),
+                delta: Point::new(30, 10),
+            })
+        );
+        trace.wait(100);
+        assert_eq!(
+            trace.moved(0, 128, 115),
+            Some(Gesture::Drag {
+                position: Point::new(128, 115),
+                delta: Point::new(-2, 5),
+            })
+        );
+        // Too slow for a swipe, and no long press while dragging
+        assert_eq!(trace.wait(1_000), None);
+        assert_eq!(
+            trace.up(0, 128, 115),
+            Some(Gesture::DragEnd(Point::new(128, 115)))
+        );
+    }
+
+    #[test]
+    fn pinch() {
+        let mut trace = Trace::new();
+        trace.down(0, 150, 200);
+        trace.wait(10);
+        assert_eq!(trace.down(1, 250, 200), None);
+        trace.wait(20);
+
+        // Twice as far apart
+        let Some(Gesture::Pinch {
+            center,
+            scale,
+            rotation,
+        }) = trace.moved(1, 350, 200)
+        else {
+            panic!("no pinch");
+        };
+        assert_eq!(center, Point::new(250, 200));
+        assert!((scale - 2.0).abs() < 1e-4, "{scale}");
+        assert!(rotation.abs() < 1e-3, "{rotation}");
+
+        // Quarter turn clockwise around the first finger
+        trace.wait(20);
+        let Some(Gesture::Pinch {
+            scale, rotation, ..
+        }) = trace.moved(1, 150, 400)
+        else {
+            panic!("no pinch");
+        };
+        assert!((scale - 2.0).abs() < 1e-4, "{scale}");
+        assert!((rotation - 90.0).abs() < 1e-3, "{rotation}");
+
+        // No taps or swipes once a second finger was down
+        assert_eq!(trace.up(1, 150, 400), None);
+        assert_eq!(trace.wait(1_000), None);
+        assert_eq!(trace.up(0, 150, 200), None);
+    }
+
+    #[test]
+    fn pinch_rotation_wraps() {
+        let mut trace = Trace::new();
+        trace.down(0, 200, 200);
+        trace.down(1, 100, 201);
+        // From just below -180 degrees to just above 180
+        let Some(Gesture::Pinch { rotation, .. }) = trace.moved(1, 100, 199) else {
+            panic!("no pinch");
+        };
+        assert!(rotation.abs() < 2.0, "{rotation}");
+    }
+
+    #[test]
+    fn normalizes_angles() {
+        assert_eq!(normalize_angle(0.0), 0.0);
+        assert_eq!(normalize_angle(270.0), -90.0);
+        assert_eq!(normalize_angle(-270.0), 90.0);
+        assert_eq!(normalize_angle(540.0), -180.0);
+    }
+}
@@ -0,0 +1,81 @@
+// The touch controller pulls its INT line low when it has a report. The GPIO
+// interrupt handler flags it for the spd2010 driver and wakes whoever is
+// waiting on `TOUCH_SIGNAL`, so nothing has to poll.
+//
+// Install `interrupt_handler` before touching the controller:
+//
+//     io.set_interrupt_handler(touch::interrupt::interrupt_handler);
+//     touch::interrupt::install(Input::new(peripherals.GPIO4, config));
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use esp_hal::{
+    gpio::{Event, Input},
+    handler,
+};
+use spd2010::touch::InterruptInput;
+
+pub struct TouchInterrupt<'a> {
+    interrupt_input: Input<'a>,
+    interrupt_flag: bool,
+}
+
+impl<'a> TouchInterrupt<'a> {
+    pub fn new(interrupt_input: Input<'a>) -> Self {
+        Self {
+            interrupt_input,
+            interrupt_flag: false,
+        }
+    }
+
+    // Returns whether the interrupt was ours
+    fn possible_interrupt(&mut self) -> bool {
+        if !self.interrupt_input.is_interrupt_set() {
+            return false;
+        }
+        self.interrupt_flag = true;
+        self.interrupt_input.clear_interrupt();
+        true
+    }
+}
+
+impl InterruptInput for TouchInterrupt<'_> {
+    fn get_interrupt_flag(&self) -> bool {
+        self.interrupt_flag
+    }
+    fn clear_interrupt_flag(&mut self) {
+        self.interrupt_flag = false
+    }
+    fn get_interrupt_state(&self) -> bool {
+        self.interrupt_input.is_high()
+    }
+}
+
+// critical_section mutex requires RefCell for interior mutability
+pub static TOUCH_INTERRUPT: Mutex<RefCell<Option<TouchInterrupt<'static>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Signalled on every falling edge of the touch INT line.
+pub static TOUCH_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Starts listening on the touch INT pin (GPIO4 on this board).
+pub fn install(mut interrupt_input: Input<'static>) {
+    interrupt_input.listen(Event::FallingEdge);
+    critical_section::with(|cs| {
+        TOUCH_INTERRUPT
+            .borrow_ref_mut(cs)
+            .replace(TouchInterrupt::new(interrupt_input));
+    });
+}
+
+#[handler]
+pub fn interrupt_handler() {
+    critical_section::with(|cs| {
+        let mut interrupt = TOUCH_INTERRUPT.borrow_ref_mut(cs);
+        if interrupt.as_mut().is_some_and(|i| i.possible_interrupt()) {
+            TOUCH_SIGNAL.signal(());
+        }
+    });
+}
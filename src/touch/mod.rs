@@ -0,0 +1,9 @@
+pub mod calibration;
+#[cfg(target_arch = "xtensa")]
+pub mod controller;
+pub mod event;
+pub mod filter;
+pub mod gesture;
+#[cfg(target_arch = "xtensa")]
+pub mod interrupt;
+pub mod record;
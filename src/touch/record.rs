@@ -0,0 +1,402 @@
+// Recording touch events to replay them later, so a UI bug caused by one
+// particular swipe can be reproduced on the device or in a test.
+//
+// The format is a 3 byte header ("TR", version) followed by one record per
+// event: a tag byte (phase << 4 | id, so ids up to 15), the milliseconds since the previous
+// event as a LEB128 varint and x, y as little endian i16s. Most events fit in
+// six bytes.
+//
+// `TouchRecorder::dump` prints a recording as hex over the serial console;
+// `decode_hex` turns that back into bytes for `TouchRecording`.
+
+use alloc::vec::Vec;
+
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::prelude::Point;
+
+use super::event::{TouchChannel, TouchEvent, TouchPhase};
+use crate::{print, println};
+
+const MAGIC: [u8; 2] = *b"TR";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+// Bytes per line of the hex dump
+const DUMP_LINE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingError {
+    /// Not a recording, or from another version
+    BadHeader,
+    /// Ends in the middle of an event
+    Truncated,
+    /// A time delta with more bytes than a u32 needs
+    VarintTooLong,
+    BadPhase(u8),
+    /// `decode_hex` found something that isn't a hex digit
+    BadHex,
+}
+
+pub struct TouchRecorder {
+    bytes: Vec<u8>,
+    last_time: Option<Instant>,
+}
+
+impl TouchRecorder {
+    pub fn new() -> Self {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        Self {
+            bytes,
+            last_time: None,
+        }
+    }
+
+    /// Appends `event`. Times are kept relative to the previous event, the
+    /// first one starts the recording. Ids only get four bits, which is
+    /// plenty for `MAX_TOUCH_POINTS` fingers.
+    pub fn record(&mut self, event: &TouchEvent) {
+        debug_assert!(event.id <= 0x0F, "touch id {} doesn't fit", event.id);
+        let delta = match self.last_time {
+            Some(last) => event.time.saturating_duration_since(last).as_millis(),
+            None => 0,
+        };
+        self.last_time = Some(event.time);
+
+        let phase = match event.phase {
+            TouchPhase::Down => 0,
+            TouchPhase::Move => 1,
+            TouchPhase::Up => 2,
+        };
+        self.bytes.push(phase << 4 | (event.id & 0x0F));
+        write_varint(&mut self.bytes, delta.min(u32::MAX as u64) as u32);
+        for coordinate in [event.position.x, event.position.y] {
+            let coordinate = coordinate.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+            self.bytes.extend_from_slice(&coordinate.to_le_bytes());
+        }
+    }
+
+    /// Header included.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.len() == HEADER_LEN
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.truncate(HEADER_LEN);
+        self.last_time = None;
+    }
+
+    /// Prints the recording as hex between two marker lines, ready to be
+    /// pasted into a test.
+    pub fn dump(&self) {
+        println!("--- touch recording, {} bytes ---", self.bytes.len());
+        for line in self.bytes.chunks(DUMP_LINE) {
+            for byte in line {
+                print!("{:02x}", byte);
+            }
+            println!();
+        }
+        println!("--- end of touch recording ---");
+    }
+}
+
+impl Default for TouchRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A recording to replay, checked up front so iterating can't fail.
+#[derive(Debug, Clone, Copy)]
+pub struct TouchRecording<'a> {
+    // Without the header
+    records: &'a [u8],
+}
+
+impl<'a> TouchRecording<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, RecordingError> {
+        let (header, records) = bytes
+            .split_at_checked(HEADER_LEN)
+            .ok_or(RecordingError::BadHeader)?;
+        if header[..MAGIC.len()] != MAGIC || header[MAGIC.len()] != VERSION {
+            return Err(RecordingError::BadHeader);
+        }
+
+        let mut rest = records;
+        while !rest.is_empty() {
+            decode_record(&mut rest)?;
+        }
+
+        Ok(Self { records })
+    }
+
+    /// The events with times counted from `start`.
+    pub fn events(&self, start: Instant) -> impl Iterator<Item = TouchEvent> + 'a {
+        let mut rest = self.records;
+        let mut time = start;
+        core::iter::from_fn(move || {
+            let (phase, id, delta, position) = decode_record(&mut rest).ok()?;
+            time += Duration::from_millis(delta as u64);
+            Some(TouchEvent {
+                phase,
+                id,
+                position,
+                time,
+            })
+        })
+    }
+
+    /// From the first event to the last.
+    pub fn duration(&self) -> Duration {
+        let start = Instant::from_ticks(0);
+        self.events(start)
+            .last()
+            .map_or(Duration::from_ticks(0), |event| event.time - start)
+    }
+
+    /// Sends the events to `events` as if they were happening now, with the
+    /// same timing as when they were recorded.
+    pub async fn replay(&self, events: &TouchChannel) {
+        for event in self.events(Instant::now()) {
+            Timer::at(event.time).await;
+            events.send(event).await;
+        }
+    }
+}
+
+/// Bytes back from the lines `TouchRecorder::dump` prints. Whitespace and
+/// the marker lines are skipped.
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, RecordingError> {
+    let mut bytes = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("---") {
+            continue;
+        }
+        let digits: Vec<u8> = line
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()
+            .ok_or(RecordingError::BadHex)?;
+        if !digits.len().is_multiple_of(2) {
+            return Err(RecordingError::BadHex);
+        }
+        bytes.extend(digits.chunks_exact(2).map(|pair| pair[0] << 4 | pair[1]));
+    }
+    Ok(bytes)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(rest: &mut &[u8]) -> Result<u32, RecordingError> {
+    let mut value = 0u32;
+    for shift in (0..32).step_by(7) {
+        let (&byte, tail) = rest.split_first().ok_or(RecordingError::Truncated)?;
+        *rest = tail;
+        value |= ((byte & 0x7F) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(RecordingError::VarintTooLong)
+}
+
+fn decode_record(rest: &mut &[u8]) -> Result<(TouchPhase, u8, u32, Point), RecordingError> {
+    let (&tag, tail) = rest.split_first().ok_or(RecordingError::Truncated)?;
+    *rest = tail;
+    let phase = match tag >> 4 {
+        0 => TouchPhase::Down,
+        1 => TouchPhase::Move,
+        2 => TouchPhase::Up,
+        _ => return Err(RecordingError::BadPhase(tag)),
+    };
+    let delta = read_varint(rest)?;
+
+    let (coordinates, tail) = rest.split_at_checked(4).ok_or(RecordingError::Truncated)?;
+    *rest = tail;
+    let x = i16::from_le_bytes([coordinates[0], coordinates[1]]);
+    let y = i16::from_le_bytes([coordinates[2], coordinates[3]]);
+
+    Ok((phase, tag & 0x0F, delta, Point::new(x as i32, y as i32)))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::String};
+
+    use embassy_futures::{block_on, join::join};
+
+    use super::*;
+    use crate::touch::gesture::{Gesture, GestureRecognizer, SwipeDirection};
+
+    fn event(phase: TouchPhase, id: u8, x: i32, y: i32, millis: u64) -> TouchEvent {
+        TouchEvent {
+            phase,
+            id,
+            position: Point::new(x, y),
+            time: Instant::from_millis(millis),
+        }
+    }
+
+    fn trace() -> [TouchEvent; 5] {
+        [
+            event(TouchPhase::Down, 0, 120, 300, 10_000),
+            event(TouchPhase::Move, 0, 125, 290, 10_016),
+            event(TouchPhase::Down, 1, 300, 80, 10_020),
+            // Long enough for a two byte varint
+            event(TouchPhase::Move, 1, -3, 411, 10_520),
+            event(TouchPhase::Up, 0, 125, 290, 10_520),
+        ]
+    }
+
+    // Hex the way `dump` prints it
+    fn hex(bytes: &[u8]) -> String {
+        let mut text = String::from("--- touch recording ---\n");
+        for line in bytes.chunks(DUMP_LINE) {
+            line.iter().for_each(|byte| text += &format!("{byte:02x}"));
+            text.push('\n');
+        }
+        text + "--- end of touch recording ---\n"
+    }
+
+    #[test]
+    fn encodes_compactly() {
+        let mut recorder = TouchRecorder::new();
+        assert!(recorder.is_empty());
+        recorder.record(&event(TouchPhase::Move, 3, 1, -2, 0));
+        assert_eq!(recorder.as_bytes(), b"TR\x01\x13\x00\x01\x00\xfe\xff");
+
+        recorder.clear();
+        assert!(recorder.is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_hex() {
+        let mut recorder = TouchRecorder::new();
+        trace().iter().for_each(|event| recorder.record(event));
+
+        let bytes = decode_hex(&hex(recorder.as_bytes())).unwrap();
+        assert_eq!(bytes, recorder.as_bytes());
+
+        let recording = TouchRecording::new(&bytes).unwrap();
+        let start = Instant::from_millis(10_000);
+        assert!(recording.events(start).eq(trace()));
+        assert_eq!(recording.duration(), Duration::from_millis(520));
+    }
+
+    #[test]
+    fn rejects_truncated_recordings() {
+        let mut recorder = TouchRecorder::new();
+        trace().iter().for_each(|event| recorder.record(event));
+        let bytes = recorder.into_bytes();
+
+        for len in HEADER_LEN + 1..bytes.len() {
+            let result = TouchRecording::new(&bytes[..len]);
+            // Cuts between events leave a shorter but valid recording
+            if let Err(error) = result {
+                assert_eq!(error, RecordingError::Truncated, "{len} bytes");
+            }
+        }
+        assert_eq!(
+            TouchRecording::new(&bytes[..bytes.len() - 1]).unwrap_err(),
+            RecordingError::Truncated
+        );
+        // The data ends in the middle of a varint
+        assert_eq!(
+            TouchRecording::new(b"TR\x01\x10\x80\x80").unwrap_err(),
+            RecordingError::Truncated
+        );
+    }
+
+    #[test]
+    fn rejects_overlong_varints() {
+        // Five bytes is all a u32 takes, the fifth can't continue
+        assert_eq!(
+            TouchRecording::new(b"TR\x01\x10\x80\x80\x80\x80\x80\x00").unwrap_err(),
+            RecordingError::VarintTooLong
+        );
+
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, u32::MAX);
+        assert_eq!(bytes, [0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        assert_eq!(read_varint(&mut &bytes[..]), Ok(u32::MAX));
+    }
+
+    #[test]
+    fn replays_a_swipe_into_the_recognizer() {
+        // What `dump` printed for a left swipe: down, three moves 20ms apart
+        // and the lift 40ms later
+        let mut recorder = TouchRecorder::new();
+        for (phase, x, y, millis) in [
+            (TouchPhase::Down, 300, 200, 0),
+            (TouchPhase::Move, 280, 202, 20),
+            (TouchPhase::Move, 240, 204, 40),
+            (TouchPhase::Move, 200, 206, 60),
+            (TouchPhase::Up, 200, 206, 100),
+        ] {
+            recorder.record(&event(phase, 0, x, y, millis));
+        }
+        let bytes = decode_hex(&hex(recorder.as_bytes())).unwrap();
+        let recording = TouchRecording::new(&bytes).unwrap();
+
+        let channel = TouchChannel::new();
+        let recognize = async {
+            let mut recognizer = GestureRecognizer::default();
+            let mut gestures = Vec::new();
+            for _ in 0..5 {
+                let event = channel.receive().await;
+                gestures.extend(recognizer.process(&event));
+            }
+            gestures
+        };
+        let ((), gestures) = block_on(join(recording.replay(&channel), recognize));
+
+        assert_eq!(gestures[0], Gesture::DragStart(Point::new(300, 200)));
+        let Some(&Gesture::Swipe {
+            direction,
+            start,
+            end,
+            velocity,
+        }) = gestures.last()
+        else {
+            panic!("{gestures:?}");
+        };
+        assert_eq!(direction, SwipeDirection::Left);
+        assert_eq!((start, end), (Point::new(300, 200), Point::new(200, 206)));
+        // Replayed with the recorded timing, so as fast as it was
+        assert!((velocity - 1001.8).abs() < 1.0, "{velocity}");
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(
+            TouchRecording::new(b"TR").unwrap_err(),
+            RecordingError::BadHeader
+        );
+        assert_eq!(
+            TouchRecording::new(b"TR\x02").unwrap_err(),
+            RecordingError::BadHeader
+        );
+        assert_eq!(
+            TouchRecording::new(b"TR\x01\x30\x00\x00\x00\x00\x00").unwrap_err(),
+            RecordingError::BadPhase(0x30)
+        );
+        assert_eq!(decode_hex("54 52 0"), Err(RecordingError::BadHex));
+        assert_eq!(decode_hex("5452 zz"), Err(RecordingError::BadHex));
+        assert_eq!(decode_hex("54 52\n01"), Ok(b"TR\x01".to_vec()));
+    }
+}
@@ -0,0 +1,5 @@
+# Runs the library tests on the host. `.cargo/config.toml` cross-compiles
+# core for the ESP32-S3, so cargo is started from outside the repo where it
+# isn't picked up. Extra arguments go to `cargo test`, e.g. a test name.
+root=$(cd "$(dirname "$0")" && pwd)
+cd / && cargo +nightly test --lib --manifest-path "$root/Cargo.toml" "$@"